@@ -10,9 +10,11 @@ extern crate uuid;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
 extern crate bincode;
 extern crate noise;
 extern crate euclid;
+extern crate rodio;
 
 mod substate;
 mod utility;
@@ -27,28 +29,44 @@ use ggez::graphics;
 
 use self::substate::mapper;
 use self::substate::{Status, SubState};
+use self::utility::{display, i18n, settings, viewport};
+use self::utility::audio;
+use self::utility::audio::AudioManager;
 
 struct MainState {
     current_substate: Box<SubState>,
     debug: bool,
     paused: bool,
     mouse_position: (i32, i32),
+    // movement too small to make a whole virtual unit yet
+    mouse_remainder: (f32, f32),
+    ui_scale: f32,
+    window_size: (u32, u32),
+    audio: AudioManager,
 }
 
 impl MainState {
-    fn new() -> GameResult<MainState> {
-        let s = MainState {
+    fn new(ctx: &mut Context) -> GameResult<MainState> {
+        let mut s = MainState {
             current_substate: mapper::from_id("menu").unwrap(),
             debug: cfg!(debug_assertions),
             paused: false,
             mouse_position: (0, 0),
+            mouse_remainder: (0.0, 0.0),
+            ui_scale: 1.0,
+            window_size: (0, 0),
+            audio: AudioManager::new(audio::default_backend()),
         };
+        s.audio.play_music(mapper::music_for("menu"));
         Ok(s)
     }
 
     fn transition_state(&mut self, id: &'static str) {
         match mapper::from_id(id) {
-            Ok(state) => self.current_substate = state,
+            Ok(state) => {
+                self.current_substate = state;
+                self.audio.play_music(mapper::music_for(id));
+            }
             Err(why) => panic!("{}", why),
         }
     }
@@ -56,6 +74,15 @@ impl MainState {
 
 impl event::EventHandler for MainState {
     fn update(&mut self, ctx: &mut Context, dt: Duration) -> GameResult<()> {
+        // graphics::set_mode doesn't raise resize events, so changes made
+        // through the display settings are caught here instead
+        let window_size = display::window_size(ctx);
+        if window_size != self.window_size {
+            self.resize_event(ctx, window_size.0, window_size.1);
+        }
+
+        self.audio.update(dt);
+
         // handle sub-state statuses and transitions
         if let Some(status) = self.current_substate.get_status() {
             match status {
@@ -96,15 +123,22 @@ impl event::EventHandler for MainState {
         Ok(())
     }
 
+    // sub-states only ever see positions in virtual units
     fn mouse_button_down_event(&mut self, button: MouseButton, x: i32, y: i32) {
+        let (x, y) = viewport::to_virtual(x, y, self.ui_scale);
         self.current_substate.mouse_button_down_event(button, x, y);
     }
 
     fn mouse_button_up_event(&mut self, button: MouseButton, x: i32, y: i32) {
+        let (x, y) = viewport::to_virtual(x, y, self.ui_scale);
         self.current_substate.mouse_button_up_event(button, x, y);
     }
 
     fn mouse_motion_event(&mut self, state: MouseState, x: i32, y: i32, xrel: i32, yrel: i32) {
+        let (x, y) = viewport::to_virtual(x, y, self.ui_scale);
+        let (xrel, yrel) =
+            viewport::to_virtual_motion(xrel, yrel, self.ui_scale, &mut self.mouse_remainder);
+
         if self.debug {
             self.mouse_position = (x, y);
         }
@@ -123,7 +157,10 @@ impl event::EventHandler for MainState {
     }
 
     fn key_down_event(&mut self, keycode: Keycode, keymod: Mod, repeat: bool) {
-        if keycode == Keycode::F3 {
+        // a sub-state capturing keys gets them all, shortcuts included
+        if !repeat && !self.current_substate.is_capturing_keys() &&
+            settings::read(|s| s.keys.matches("toggle_debug", keycode))
+        {
             self.debug = !self.debug;
         }
 
@@ -165,6 +202,12 @@ impl event::EventHandler for MainState {
         self.current_substate.focus_event(gained);
     }
 
+    fn resize_event(&mut self, ctx: &mut Context, width: u32, height: u32) {
+        self.window_size = (width, height);
+        self.ui_scale = viewport::apply(ctx, width, height);
+        self.current_substate.resize_event(ctx, width, height);
+    }
+
     fn quit_event(&mut self) -> bool {
         self.current_substate.quit_event()
     }
@@ -178,14 +221,23 @@ pub fn main() {
         window_height: 768,
         window_width: 1024,
         vsync: false,
-        resizable: false,
+        resizable: true,
     };
 
     // load window context
     let ctx = &mut Context::load_from_conf("aschuhardt", "rl", c).unwrap();
 
+    // switch to the user's saved display mode
+    settings::load(ctx.filesystem.get_user_data_dir());
+    if let Err(e) = display::apply(ctx, &settings::get().display) {
+        println!("Unable to apply display settings: {}", e);
+    }
+    if let Err(e) = i18n::set_locale(&settings::get().gameplay.locale) {
+        println!("Unable to load the saved language: {}", e);
+    }
+
     // instantiate main state
-    let state = &mut MainState::new().unwrap();
+    let state = &mut MainState::new(ctx).unwrap();
 
     // start game loop
     if let Err(e) = event::run(ctx, state) {
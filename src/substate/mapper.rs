@@ -6,10 +6,21 @@ pub fn from_id(id: &'static str) -> Result<Box<SubState>, String> {
         "menu" => Ok(Box::new(states::MenuState::new())),
         "mapgen" => Ok(Box::new(states::MapGenState::new())),
         "about" => Ok(Box::new(states::AboutState::new())),
+        "display" => Ok(Box::new(states::DisplayState::new())),
+        "options" => Ok(Box::new(states::OptionsState::new())),
         _ => Err(format!("No sub-state was found with ID {}!", id)),
     }
 }
 
+// the music that plays while a sub-state is open, if any
+pub fn music_for(id: &str) -> Option<&'static str> {
+    match id {
+        "menu" | "about" | "options" | "display" => Some("music_menu"),
+        "mapgen" => Some("music_mapgen"),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use substate::mapper;
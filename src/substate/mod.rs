@@ -12,4 +12,8 @@ pub enum Status {
 
 pub trait SubState: EventHandler {
     fn get_status(&mut self) -> Option<Status>;
+    // see StateInfo::capture_keys
+    fn is_capturing_keys(&self) -> bool {
+        false
+    }
 }
@@ -7,6 +7,12 @@ use ggez::graphics;
 use substate::states::StateInfo;
 use substate::{Status, SubState};
 use utility::ui;
+use utility::settings;
+
+const ABOUT_TOP_PAD: f32 = 100.0;
+const ABOUT_MAX_WIDTH: f32 = 480.0;
+const CREDITS_TOP_PAD: f32 = 24.0;
+const CREDITS_HEIGHT: f32 = 220.0;
 
 pub struct AboutState {
     info: StateInfo,
@@ -26,22 +32,21 @@ impl AboutState {
     fn init_ui(&mut self, ctx: &mut Context) {
         graphics::set_background_color(ctx, graphics::BLACK);
 
-        self.ui_context.add_element(
-            "lbl_about",
-            Box::new(ui::Label::new(
-                String::from("This game was made by Addison Schuhardt."),
-            )),
-        );
+        let mut about = ui::RichLabel::localized("about.text");
+        about.set_max_width(ABOUT_MAX_WIDTH);
+        about.set_alignment(ui::Alignment::Center);
+        self.ui_context.add_element("lbl_about", Box::new(about));
 
-        self.ui_context.add_element(
-            "lbl_contact",
-            Box::new(ui::Label::new(String::from("Contact: a@schuhardt.net"))),
-        );
+        let mut credits_text = ui::RichLabel::localized("about.credits");
+        credits_text.set_alignment(ui::Alignment::Center);
+        let mut credits = ui::ScrollPanel::new(credits_text);
+        credits.set_size(ABOUT_MAX_WIDTH, CREDITS_HEIGHT);
+        self.ui_context.add_element("pnl_credits", Box::new(credits));
 
         self.ui_context.add_element(
             "btn_menu",
-            Box::new(ui::Button::new(
-                String::from("Back to Main Menu"),
+            Box::new(ui::Button::localized(
+                "about.back",
                 |state: &mut StateInfo| { state.transition("menu"); },
             )),
         );
@@ -58,22 +63,29 @@ impl SubState for AboutState {
 
 impl event::EventHandler for AboutState {
     fn update(&mut self, ctx: &mut Context, dt: Duration) -> GameResult<()> {
+        self.ui_context.update(dt);
 
         //check to see if ui needs to be updated after having been initialized
-        if self.info.is_ui_dirty() && self.has_initialized_ui {
+        if (self.info.is_ui_dirty() | self.ui_context.is_dirty()) && self.has_initialized_ui {
             let screen = graphics::get_screen_coordinates(ctx);
 
+            let mut about_height: f32 = 0.0;
             self.ui_context.modify_element(
                 "lbl_about",
-                |lbl: &mut ui::Label| {
-                    lbl.set_position(screen.w / 2.0, 100.0);
+                |lbl: &mut ui::RichLabel| {
+                    about_height = lbl.get_height();
+                    lbl.set_position(screen.w / 2.0, ABOUT_TOP_PAD + about_height / 2.0);
                 },
             );
 
             self.ui_context.modify_element(
-                "lbl_contact",
-                |lbl: &mut ui::Label| {
-                    lbl.set_position(screen.w / 2.0, 150.0);
+                "pnl_credits",
+                |pnl: &mut ui::ScrollPanel| {
+                    let height = pnl.get_height();
+                    pnl.set_position(
+                        screen.w / 2.0,
+                        ABOUT_TOP_PAD + about_height + CREDITS_TOP_PAD + height / 2.0,
+                    );
                 },
             );
 
@@ -112,14 +124,32 @@ impl event::EventHandler for AboutState {
         xrel: i32,
         yrel: i32,
     ) {
-        if !state.left() {
-            self.ui_context.mouse_moved(x, y);
-        }
+        let input = ui::Input::MouseMotion(state, x, y, xrel, yrel);
+        self.ui_context.handle_input(input, &mut self.info);
     }
 
     fn mouse_button_down_event(&mut self, button: event::MouseButton, x: i32, y: i32) {
-        if button == event::MouseButton::Left {
-            self.ui_context.click(x, y, &mut self.info);
+        self.ui_context.handle_input(ui::Input::MouseDown(button, x, y), &mut self.info);
+    }
+
+    fn mouse_button_up_event(&mut self, button: event::MouseButton, x: i32, y: i32) {
+        self.ui_context.handle_input(ui::Input::MouseUp(button, x, y), &mut self.info);
+    }
+
+    fn mouse_wheel_event(&mut self, x: i32, y: i32) {
+        self.ui_context.handle_input(ui::Input::Wheel(y), &mut self.info);
+    }
+
+    fn key_down_event(&mut self, keycode: event::Keycode, keymod: event::Mod, repeat: bool) {
+        if settings::read(|s| s.keys.matches("back", keycode)) {
+            self.info.transition("menu");
+            return;
         }
+
+        self.ui_context.handle_input(ui::Input::KeyDown(keycode), &mut self.info);
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: u32, height: u32) {
+        self.info.refresh_ui();
     }
 }
@@ -0,0 +1,340 @@
+use std::time::Duration;
+
+use ggez::{GameResult, Context};
+use ggez::event;
+use ggez::graphics;
+
+use substate::states::{StateInfo, StoredValue};
+use substate::{Status, SubState};
+use utility::ui;
+use utility::fonts;
+use utility::i18n;
+use utility::display;
+use utility::settings;
+use utility::settings::{DisplaySettings, DisplayMode};
+
+const TITLE_TOP_PAD: f32 = 48.0;
+const TITLE_FONT_SIZE: u32 = 32;
+const SECTION_TOP_PAD: f32 = 140.0;
+const ROW_SPACING: f32 = 36.0;
+const BTN_WIDTH: f32 = 120.0;
+const BTN_SPACING: f32 = 6.0;
+const LIST_WIDTH: f32 = 240.0;
+const LIST_HEIGHT: f32 = 240.0;
+
+const MODE_KEY: &'static str = "display_mode";
+const RESOLUTION_KEY: &'static str = "display_resolution";
+const APPLY_KEY: &'static str = "display_apply";
+const ERROR_DIALOG_ID: &'static str = "dlg_display_error";
+
+const MODE_BUTTONS: [&'static str; 3] = ["btn_windowed", "btn_fullscreen", "btn_borderless"];
+
+pub struct DisplayState {
+    info: StateInfo,
+    has_initialized_ui: bool,
+    ui_context: ui::UIContext,
+    pending: DisplaySettings,
+    resolutions: Vec<(u32, u32)>,
+}
+
+impl DisplayState {
+    pub fn new() -> DisplayState {
+        DisplayState {
+            info: StateInfo::new(),
+            has_initialized_ui: false,
+            ui_context: ui::UIContext::new(),
+            pending: settings::get().display,
+            resolutions: Vec::<(u32, u32)>::new(),
+        }
+    }
+
+    fn init_ui(&mut self, ctx: &mut Context) {
+        graphics::set_background_color(ctx, graphics::BLACK);
+
+        let mut title = ui::Label::localized("display.title");
+        title.set_font(fonts::DEFAULT_FONT_NAME, TITLE_FONT_SIZE);
+        self.ui_context.add_element("lbl_title", Box::new(title));
+
+        self.ui_context.add_element(
+            "lbl_mode",
+            Box::new(ui::Label::new(String::from("..."))),
+        );
+
+        self.ui_context.add_element(
+            "btn_windowed",
+            Box::new(ui::Button::localized(
+                "display.windowed",
+                |state: &mut StateInfo| { DisplayState::select_mode(state, DisplayMode::Windowed); },
+            )),
+        );
+
+        self.ui_context.add_element(
+            "btn_fullscreen",
+            Box::new(ui::Button::localized(
+                "display.fullscreen",
+                |state: &mut StateInfo| { DisplayState::select_mode(state, DisplayMode::Fullscreen); },
+            )),
+        );
+
+        self.ui_context.add_element(
+            "btn_borderless",
+            Box::new(ui::Button::localized(
+                "display.borderless",
+                |state: &mut StateInfo| { DisplayState::select_mode(state, DisplayMode::Borderless); },
+            )),
+        );
+
+        self.ui_context.add_element(
+            "lbl_resolution",
+            Box::new(ui::Label::new(String::from("..."))),
+        );
+
+        // the current resolution is offered even if the display doesn't list
+        // it, since it's what a resizable window may well have ended up at
+        self.resolutions = display::available_resolutions(ctx);
+        let current = (self.pending.width, self.pending.height);
+        if !self.resolutions.contains(&current) {
+            self.resolutions.insert(0, current);
+        }
+
+        let items = self.resolutions
+            .iter()
+            .map(|&(w, h)| format!("{} x {}", w, h))
+            .collect();
+        let mut list = ui::ListView::new(items);
+        list.set_size(LIST_WIDTH, LIST_HEIGHT);
+        list.set_selected(self.resolutions.iter().position(|r| *r == current));
+        list.set_callback(DisplayState::select_resolution);
+        self.ui_context.add_element("lst_resolutions", Box::new(list));
+
+        self.ui_context.add_element(
+            "btn_apply",
+            Box::new(ui::Button::localized(
+                "display.apply",
+                |state: &mut StateInfo| {
+                    state.set_value(APPLY_KEY, StoredValue::Boolean { value: true });
+                    state.refresh_ui();
+                },
+            )),
+        );
+
+        self.ui_context.add_element(
+            "btn_back",
+            Box::new(ui::Button::localized(
+                "display.back",
+                |state: &mut StateInfo| { state.transition("options"); },
+            )),
+        );
+
+        self.has_initialized_ui = true;
+    }
+
+    fn select_mode(state: &mut StateInfo, mode: DisplayMode) {
+        state.set_value(
+            MODE_KEY,
+            StoredValue::Textual { value: String::from(mode.name()) },
+        );
+        state.refresh_ui();
+    }
+
+    fn select_resolution(state: &mut StateInfo, index: usize) {
+        state.set_value(RESOLUTION_KEY, StoredValue::Unsigned { value: index });
+        state.refresh_ui();
+    }
+
+    // switches to the pending display settings and saves them, going back to
+    // the previous settings if the switch fails
+    fn apply(&mut self, ctx: &mut Context) {
+        let mut current = settings::get();
+
+        match display::apply(ctx, &self.pending) {
+            Ok(()) => {
+                current.display = self.pending.clone();
+                if let Err(why) = settings::set(current) {
+                    println!("Unable to save settings: {}", why);
+                }
+            }
+            Err(why) => {
+                if let Err(why) = display::apply(ctx, &current.display) {
+                    println!("Unable to restore the previous display mode: {}", why);
+                }
+                self.pending = current.display;
+
+                let mut dialog = ui::Dialog::new(
+                    ERROR_DIALOG_ID,
+                    &i18n::tr("display.error_title"),
+                    &i18n::tr_args("display.error_message", &[why.to_string()]),
+                );
+                dialog.add_localized_button("dialog.ok", ui::CHOICE_YES);
+                self.ui_context.show_dialog(dialog);
+            }
+        }
+    }
+}
+
+impl SubState for DisplayState {
+    fn get_status(&mut self) -> Option<Status> {
+        self.info.get_status()
+    }
+}
+
+impl event::EventHandler for DisplayState {
+    fn update(&mut self, ctx: &mut Context, dt: Duration) -> GameResult<()> {
+        self.ui_context.update(dt);
+
+        //check to see if ui needs to be updated after having been initialized
+        if (self.info.is_ui_dirty() | self.ui_context.is_dirty()) && self.has_initialized_ui {
+            let screen = graphics::get_screen_coordinates(ctx);
+
+            if let Some(StoredValue::Textual { value: name }) = self.info.take_value(MODE_KEY) {
+                if let Some(mode) = DisplayMode::from_name(&name) {
+                    self.pending.mode = mode;
+                }
+            }
+
+            if let Some(StoredValue::Unsigned { value: index }) =
+                self.info.take_value(RESOLUTION_KEY)
+            {
+                if let Some(&(width, height)) = self.resolutions.get(index) {
+                    self.pending.width = width;
+                    self.pending.height = height;
+                }
+            }
+
+            if let Some(StoredValue::Boolean { value: true }) = self.info.take_value(APPLY_KEY) {
+                self.apply(ctx);
+            }
+
+            //the error dialog only has the one button, so there's nothing to do
+            self.info.take_value(ERROR_DIALOG_ID);
+
+            self.ui_context.modify_element(
+                "lbl_title",
+                |lbl: &mut ui::Label| {
+                    let height = lbl.get_height();
+                    lbl.set_position(screen.w / 2.0, TITLE_TOP_PAD + height / 2.0);
+                },
+            );
+
+            let mode_name = i18n::tr(&format!("display.{}", self.pending.mode.name()));
+            self.ui_context.modify_element(
+                "lbl_mode",
+                |lbl: &mut ui::Label| {
+                    lbl.set_text(i18n::tr_args("display.mode", &[mode_name.clone()]), ctx);
+                    lbl.set_position(screen.w / 2.0, SECTION_TOP_PAD);
+                },
+            );
+
+            let row_width = 3.0 * BTN_WIDTH + 2.0 * BTN_SPACING;
+            for (i, name) in MODE_BUTTONS.iter().enumerate() {
+                self.ui_context.modify_element(
+                    name,
+                    |btn: &mut ui::Button| {
+                        btn.set_width(BTN_WIDTH);
+                        btn.set_position(
+                            (screen.w - row_width + BTN_WIDTH) / 2.0 +
+                                i as f32 * (BTN_WIDTH + BTN_SPACING),
+                            SECTION_TOP_PAD + ROW_SPACING,
+                        );
+                    },
+                );
+            }
+
+            let (width, height) = (self.pending.width, self.pending.height);
+            self.ui_context.modify_element(
+                "lbl_resolution",
+                |lbl: &mut ui::Label| {
+                    lbl.set_text(
+                        i18n::tr_args(
+                            "display.resolution",
+                            &[width.to_string(), height.to_string()],
+                        ),
+                        ctx,
+                    );
+                    lbl.set_position(screen.w / 2.0, SECTION_TOP_PAD + 2.5 * ROW_SPACING);
+                },
+            );
+
+            let list_top = SECTION_TOP_PAD + 3.0 * ROW_SPACING;
+            self.ui_context.modify_element(
+                "lst_resolutions",
+                |list: &mut ui::ListView| {
+                    list.set_position(screen.w / 2.0, list_top + LIST_HEIGHT / 2.0);
+                },
+            );
+
+            let buttons_y = list_top + LIST_HEIGHT + ROW_SPACING;
+            self.ui_context.modify_element(
+                "btn_apply",
+                |btn: &mut ui::Button| {
+                    btn.set_width(BTN_WIDTH);
+                    btn.set_position(
+                        screen.w / 2.0 - (BTN_WIDTH + BTN_SPACING) / 2.0,
+                        buttons_y,
+                    );
+                },
+            );
+
+            self.ui_context.modify_element(
+                "btn_back",
+                |btn: &mut ui::Button| {
+                    btn.set_width(BTN_WIDTH);
+                    btn.set_position(
+                        screen.w / 2.0 + (BTN_WIDTH + BTN_SPACING) / 2.0,
+                        buttons_y,
+                    );
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        if !self.has_initialized_ui {
+            self.init_ui(ctx);
+            self.info.refresh_ui();
+        }
+
+        self.ui_context.draw(ctx);
+
+        Ok(())
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        state: event::MouseState,
+        x: i32,
+        y: i32,
+        xrel: i32,
+        yrel: i32,
+    ) {
+        let input = ui::Input::MouseMotion(state, x, y, xrel, yrel);
+        self.ui_context.handle_input(input, &mut self.info);
+    }
+
+    fn mouse_button_down_event(&mut self, button: event::MouseButton, x: i32, y: i32) {
+        self.ui_context.handle_input(ui::Input::MouseDown(button, x, y), &mut self.info);
+    }
+
+    fn mouse_button_up_event(&mut self, button: event::MouseButton, x: i32, y: i32) {
+        self.ui_context.handle_input(ui::Input::MouseUp(button, x, y), &mut self.info);
+    }
+
+    fn mouse_wheel_event(&mut self, x: i32, y: i32) {
+        self.ui_context.handle_input(ui::Input::Wheel(y), &mut self.info);
+    }
+
+    fn key_down_event(&mut self, keycode: event::Keycode, keymod: event::Mod, repeat: bool) {
+        if !self.ui_context.has_dialog() && settings::read(|s| s.keys.matches("back", keycode)) {
+            self.info.transition("options");
+            return;
+        }
+
+        self.ui_context.handle_input(ui::Input::KeyDown(keycode), &mut self.info);
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: u32, height: u32) {
+        self.info.refresh_ui();
+    }
+}
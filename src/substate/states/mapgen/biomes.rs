@@ -0,0 +1,309 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+use serde_json;
+
+use utility::theme::ThemeColor;
+use super::map::{BiomeType, TileType};
+use super::seed;
+
+pub const BIOMES_FILE: &'static str = "resources/biomes.json";
+// used if the file is missing or broken
+const DEFAULT_BIOMES: &'static str = include_str!("../../../../resources/biomes.json");
+
+// the kind of ground a biome can appear on; land takes in steep ground too
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Terrain {
+    Water,
+    Land,
+    Steep,
+}
+
+// what the ground of a biome is made of, from the surface down; anything
+// deeper than `depth` is stone
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+pub struct Materials {
+    pub surface: TileType,
+    pub subsurface: TileType,
+    // in place of both of the above when the ground is below the sea
+    pub underwater: TileType,
+    pub depth: u32,
+}
+
+fn full_range() -> [f32; 2] {
+    [-10.0, 10.0]
+}
+
+fn unit_range() -> [f32; 2] {
+    [0.0, 1.0]
+}
+
+fn default_roughness() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct BiomeDef {
+    pub id: String,
+    // message key for the biome's name
+    pub name: String,
+    pub color: ThemeColor,
+    pub terrain: Terrain,
+    // the climate and height a biome appears in; temperature and moisture run
+    // from 0 to 1, and height is on the terrain noise's scale
+    #[serde(default = "unit_range")]
+    pub temperature: [f32; 2],
+    #[serde(default = "unit_range")]
+    pub moisture: [f32; 2],
+    #[serde(default = "full_range")]
+    pub height: [f32; 2],
+    // raises or lowers the ground, and scales the bumps in it
+    #[serde(default)]
+    pub elevation: f32,
+    #[serde(default = "default_roughness")]
+    pub roughness: f32,
+    pub materials: Materials,
+    // mixed into the map seed for the biome's own noise
+    pub seed_salt: u64,
+}
+
+impl BiomeDef {
+    pub fn get_biome_type(&self) -> BiomeType {
+        BiomeType::new(&self.id)
+    }
+
+    fn accepts(&self, terrain: Terrain, height: f32, temperature: f32, moisture: f32) -> bool {
+        let within = |range: &[f32; 2], value: f32| value >= range[0] && value <= range[1];
+        self.fits(terrain) && within(&self.height, height) &&
+            within(&self.temperature, temperature) && within(&self.moisture, moisture)
+    }
+
+    fn fits(&self, terrain: Terrain) -> bool {
+        match (self.terrain, terrain) {
+            (Terrain::Land, Terrain::Steep) => true,
+            (a, b) => a == b,
+        }
+    }
+}
+
+// every biome the world can have, in the order they're tried when picking one
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct BiomeRegistry {
+    pub biomes: Vec<BiomeDef>,
+    // a Whittaker-style table of biome ids for ordinary land, by temperature
+    // (rows, cold to hot) and moisture (columns, dry to wet); biomes that
+    // aren't in it are tried first, by their own ranges
+    #[serde(default)]
+    pub climate_table: Vec<Vec<String>>,
+    // changes whenever anything above does, so maps can tell whether they
+    // were made with these biomes
+    #[serde(skip)]
+    hash: u64,
+}
+
+impl BiomeRegistry {
+    pub fn load() -> BiomeRegistry {
+        match BiomeRegistry::from_file(BIOMES_FILE) {
+            Ok(registry) => registry,
+            Err(why) => {
+                println!("Unable to load the biomes, using the built-in ones: {}", why);
+                BiomeRegistry::from_json(DEFAULT_BIOMES).unwrap()
+            }
+        }
+    }
+
+    pub fn from_json(contents: &str) -> Result<BiomeRegistry, String> {
+        let mut registry: BiomeRegistry = serde_json::from_str(contents).map_err(
+            |e| format!("Invalid biomes: {}", e),
+        )?;
+        registry.validate()?;
+        // taken from the biomes as read rather than the file, so reformatting
+        // the file doesn't count as a change
+        let canonical = serde_json::to_string(&registry).map_err(|e| e.to_string())?;
+        registry.hash = seed::hash(canonical.as_bytes());
+        Ok(registry)
+    }
+
+    pub fn get_hash(&self) -> u64 {
+        self.hash
+    }
+
+    pub fn from_file(path: &str) -> Result<BiomeRegistry, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut contents = String::new();
+        BufReader::new(file).read_to_string(&mut contents).map_err(
+            |e| {
+                format!("{}: {}", path, e)
+            },
+        )?;
+        BiomeRegistry::from_json(&contents).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let mut ids = HashSet::<&str>::new();
+        for def in self.biomes.iter() {
+            if !ids.insert(&def.id) {
+                return Err(format!("Biome \"{}\" is defined twice", def.id));
+            }
+        }
+
+        let columns = self.climate_table.first().map_or(0, |row| row.len());
+        for row in self.climate_table.iter() {
+            if row.len() != columns || columns == 0 {
+                return Err(String::from("Climate table rows must all be the same length"));
+            }
+            for id in row.iter() {
+                if !ids.contains(id.as_str()) {
+                    return Err(format!("Climate table refers to unknown biome \"{}\"", id));
+                }
+            }
+        }
+
+        for terrain in [Terrain::Water, Terrain::Land].iter() {
+            if !self.biomes.iter().any(|b| b.terrain == *terrain) {
+                return Err(format!("There are no biomes for {:?} terrain", terrain));
+            }
+        }
+        Ok(())
+    }
+
+    // a biome that isn't in the registry (from a map saved with a different
+    // set of biomes, say) is treated as the first one
+    pub fn get(&self, biome: &BiomeType) -> &BiomeDef {
+        self.biomes
+            .iter()
+            .find(|b| b.id == biome.id())
+            .unwrap_or(&self.biomes[0])
+    }
+
+    // the first biome outside the climate table that suits the ground and
+    // climate, then the table's biome for the climate on land, or failing
+    // both the first biome that suits the ground at all
+    pub fn select(&self, terrain: Terrain, height: f32, temperature: f32, moisture: f32) -> BiomeType {
+        self.biomes
+            .iter()
+            .find(|b| {
+                !self.is_in_table(b) && b.accepts(terrain, height, temperature, moisture)
+            })
+            .or_else(|| if terrain == Terrain::Water {
+                None
+            } else {
+                self.from_table(temperature, moisture)
+            })
+            .or_else(|| self.biomes.iter().find(|b| b.fits(terrain)))
+            .unwrap_or(&self.biomes[0])
+            .get_biome_type()
+    }
+
+    fn is_in_table(&self, def: &BiomeDef) -> bool {
+        self.climate_table.iter().any(
+            |row| row.iter().any(|id| *id == def.id),
+        )
+    }
+
+    fn from_table(&self, temperature: f32, moisture: f32) -> Option<&BiomeDef> {
+        let band = |value: f32, count: usize| ((value.max(0.0) * count as f32) as usize).min(count - 1);
+        let row = match self.climate_table.len() {
+            0 => return None,
+            rows => &self.climate_table[band(temperature, rows)],
+        };
+        let id = &row[band(moisture, row.len())];
+        self.biomes.iter().find(|b| b.id == *id)
+    }
+}
+
+lazy_static! {
+    static ref REGISTRY: BiomeRegistry = BiomeRegistry::load();
+}
+
+pub fn registry() -> &'static BiomeRegistry {
+    &REGISTRY
+}
+
+pub fn get(biome: &BiomeType) -> &'static BiomeDef {
+    REGISTRY.get(biome)
+}
+
+#[cfg(test)]
+mod test {
+    use utility::i18n;
+    use super::super::map::BiomeType;
+    use super::{BiomeRegistry, Terrain, BIOMES_FILE, DEFAULT_BIOMES};
+
+    #[test]
+    fn biomes_file_is_valid_and_named() {
+        let registry = BiomeRegistry::from_file(BIOMES_FILE).unwrap();
+        for id in ["ocean", "grassland", "forest", "swamp", "tundra", "mountain"].iter() {
+            assert!(registry.biomes.iter().any(|b| b.id == *id), "No {} biome", id);
+        }
+        for def in registry.biomes.iter() {
+            assert!(i18n::tr(&def.name) != def.name, "{} has no name", def.id);
+        }
+
+        //the built-in copy is what's used if the file can't be loaded
+        assert!(BiomeRegistry::from_json(DEFAULT_BIOMES).is_ok());
+    }
+
+    #[test]
+    fn biomes_are_picked_by_ground_and_climate() {
+        let registry = BiomeRegistry::from_json(
+            r#"{ "biomes": [
+                { "id": "sea", "name": "biome.ocean", "color": [0, 0, 255], "terrain": "water",
+                  "materials": { "surface": "sand", "subsurface": "sand", "underwater": "sand", "depth": 2 },
+                  "seed_salt": 3 },
+                { "id": "peak", "name": "biome.mountain", "color": [9, 9, 9], "terrain": "land",
+                  "height": [0.5, 10.0],
+                  "materials": { "surface": "stone", "subsurface": "stone", "underwater": "stone", "depth": 1 },
+                  "seed_salt": 5 },
+                { "id": "desert", "name": "biome.arid", "color": [200, 160, 90], "terrain": "land",
+                  "temperature": [0.6, 1.0], "moisture": [0.0, 0.3],
+                  "materials": { "surface": "sand", "subsurface": "sand", "underwater": "sand", "depth": 3 },
+                  "seed_salt": 7 },
+                { "id": "field", "name": "biome.grassland", "color": [0, 255, 0], "terrain": "land",
+                  "materials": { "surface": "grass", "subsurface": "soil", "underwater": "sand", "depth": 4 },
+                  "seed_salt": 11 }
+            ] }"#,
+        ).unwrap();
+
+        let pick = |terrain, height, temperature, moisture| {
+            registry.select(terrain, height, temperature, moisture)
+        };
+        assert_eq!(pick(Terrain::Water, -0.5, 0.5, 0.5), BiomeType::new("sea"));
+        assert_eq!(pick(Terrain::Steep, 0.7, 0.5, 0.5), BiomeType::new("peak"));
+        assert_eq!(pick(Terrain::Land, 0.2, 0.8, 0.1), BiomeType::new("desert"));
+        assert_eq!(pick(Terrain::Land, 0.2, 0.8, 0.6), BiomeType::new("field"));
+
+        //unknown biomes fall back to the first
+        assert_eq!(registry.get(&BiomeType::new("lava")).id, "sea");
+    }
+
+    #[test]
+    fn ordinary_land_is_looked_up_in_the_climate_table() {
+        let registry = BiomeRegistry::from_file(BIOMES_FILE).unwrap();
+        let pick = |temperature, moisture| {
+            registry.select(Terrain::Land, 0.3, temperature, moisture)
+        };
+
+        assert_eq!(pick(0.05, 0.5), BiomeType::new("tundra"));
+        assert_eq!(pick(0.95, 0.05), BiomeType::new("arid"));
+        assert_eq!(pick(0.95, 0.95), BiomeType::new("rainforest"));
+        assert_eq!(pick(0.6, 0.1), BiomeType::new("grassland"));
+
+        //biomes outside the table still come first where they fit
+        assert_eq!(registry.select(Terrain::Land, 0.6, 0.5, 0.5), BiomeType::new("mountain"));
+        assert_eq!(registry.select(Terrain::Water, -0.5, 0.5, 0.5), BiomeType::new("ocean"));
+    }
+
+    #[test]
+    fn the_hash_follows_the_biomes_rather_than_the_file() {
+        let hash = BiomeRegistry::from_json(DEFAULT_BIOMES).unwrap().get_hash();
+
+        let spaced = DEFAULT_BIOMES.replace("\": ", "\":    ");
+        assert_eq!(BiomeRegistry::from_json(&spaced).unwrap().get_hash(), hash);
+
+        let changed = DEFAULT_BIOMES.replacen("\"seed_salt\": ", "\"seed_salt\": 1", 1);
+        assert!(BiomeRegistry::from_json(&changed).unwrap().get_hash() != hash);
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+use std::thread::JoinHandle;
+
+use super::map::Map;
+
+enum Message {
+    Progress(i32),
+    Finished(Map),
+}
+
+// a map being generated on a worker thread; dropping the generation cancels it
+pub struct Generation {
+    receiver: Receiver<Message>,
+    cancelled: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+    progress: i32,
+}
+
+impl Generation {
+    // takes a map that's been set up with its size and seed, and fills it in
+    pub fn start(mut map: Map) -> Generation {
+        let (sender, receiver) = channel::<Message>();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let flag = cancelled.clone();
+        let worker = thread::spawn(move || {
+            let mut last_progress = -1;
+            let finished = map.generate_regions(|progress| {
+                // only changes are worth sending; most regions don't move the
+                // percentage along
+                if progress != last_progress {
+                    last_progress = progress;
+                    if sender.send(Message::Progress(progress)).is_err() {
+                        return false;
+                    }
+                }
+                !flag.load(Ordering::Relaxed)
+            });
+
+            if finished && !flag.load(Ordering::Relaxed) {
+                sender.send(Message::Finished(map)).ok();
+            }
+        });
+
+        Generation {
+            receiver: receiver,
+            cancelled: cancelled,
+            worker: Some(worker),
+            progress: 0,
+        }
+    }
+
+    // from 0 to 100, as of the last poll
+    pub fn get_progress(&self) -> i32 {
+        self.progress
+    }
+
+    pub fn cancel(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    // catches up on what the worker has sent, returning the map once it's done
+    pub fn poll(&mut self) -> Option<Map> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Message::Progress(progress)) => self.progress = progress,
+                Ok(Message::Finished(map)) => {
+                    self.join();
+                    return Some(map);
+                }
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => {
+                    self.join();
+                    return None;
+                }
+            }
+        }
+    }
+
+    // true once the worker has stopped, whether it finished or was cancelled
+    pub fn is_done(&self) -> bool {
+        self.worker.is_none()
+    }
+
+    fn join(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                println!("Map generation failed");
+            }
+        }
+    }
+}
+
+impl Drop for Generation {
+    // the worker notices within a region or so; there's no need to wait for it
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::super::map::Map;
+    use super::Generation;
+
+    fn wait_for(generation: &mut Generation) -> Option<Map> {
+        for _ in 0..1000 {
+            if let Some(map) = generation.poll() {
+                return Some(map);
+            }
+            if generation.is_done() {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("Generation never finished");
+    }
+
+    #[test]
+    fn maps_are_generated_in_the_background() {
+        let mut map = Map::new(6, 4, 8);
+        map.set_seed(5);
+        let mut generation = Generation::start(map);
+
+        let map = wait_for(&mut generation).unwrap();
+        assert_eq!(generation.get_progress(), 100);
+        assert_eq!(map.get_seed(), 5);
+        map.get_biome_at_offset(5, 3);
+    }
+
+    #[test]
+    fn cancelled_generations_never_finish() {
+        let mut map = Map::new(200, 200, 8);
+        map.set_seed(5);
+        let mut generation = Generation::start(map);
+        generation.cancel();
+
+        assert!(wait_for(&mut generation).is_none());
+        assert!(generation.get_progress() < 100);
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::HashSet;
+
+// only ground at least this high can be the source of a river
+const SOURCE_MIN_HEIGHT: f32 = 0.3;
+// no two sources are closer than this many regions
+const SOURCE_SPACING: i32 = 6;
+// at most one river for this many regions of map
+const REGIONS_PER_RIVER: usize = 60;
+// a basin holding more regions than this has no way out, and the river ends
+const MAX_LAKE_SIZE: usize = 6;
+
+const NEIGHBORS: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+// half of a river's course through a region, from its middle to the edge (or
+// corner) it shares with the neighbor in `direction`; the neighbor has the
+// other half, so the two meet at the border
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+pub struct RiverSegment {
+    pub direction: (i32, i32),
+    // how many rivers have joined up by this point
+    pub flow: u32,
+}
+
+// rivers and lakes for every region of the map, indexed [x][y]
+pub struct Hydrology {
+    pub rivers: Vec<Vec<Vec<RiverSegment>>>,
+    // the water level of the lake covering a region, if there is one
+    pub lakes: Vec<Vec<Option<f32>>>,
+}
+
+type Cell = (usize, usize);
+
+struct Tracer<'a> {
+    heights: &'a Vec<Vec<f32>>,
+    sea_level: f32,
+    width: usize,
+    height: usize,
+    downstream: Vec<Vec<Option<Cell>>>,
+    flow: Vec<Vec<u32>>,
+    lakes: Vec<Vec<Option<f32>>>,
+    // where water leaves each lake; None for lakes with no way out
+    lake_exits: Vec<Vec<Option<Cell>>>,
+}
+
+// runs rivers downhill from the high ground to the sea, pooling them into
+// lakes wherever they get stuck in a basin; heights are indexed [x][y]
+pub fn trace(heights: &Vec<Vec<f32>>, sea_level: f32) -> Hydrology {
+    let width = heights.len();
+    let height = heights.first().map_or(0, |c| c.len());
+
+    let mut tracer = Tracer {
+        heights: heights,
+        sea_level: sea_level,
+        width: width,
+        height: height,
+        downstream: vec![vec![None; height]; width],
+        flow: vec![vec![0; height]; width],
+        lakes: vec![vec![None; height]; width],
+        lake_exits: vec![vec![None; height]; width],
+    };
+
+    for source in tracer.find_sources() {
+        tracer.trace_river(source);
+    }
+    tracer.into_hydrology()
+}
+
+impl<'a> Tracer<'a> {
+    fn get_height(&self, cell: Cell) -> f32 {
+        self.heights[cell.0][cell.1]
+    }
+
+    fn is_sea(&self, cell: Cell) -> bool {
+        self.get_height(cell) <= self.sea_level
+    }
+
+    fn neighbors(&self, cell: Cell) -> Vec<Cell> {
+        let mut cells = Vec::<Cell>::new();
+        for &(dx, dy) in NEIGHBORS.iter() {
+            let x = cell.0 as i32 + dx;
+            let y = cell.1 as i32 + dy;
+            if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+                cells.push((x as usize, y as usize));
+            }
+        }
+        cells
+    }
+
+    // the highest ground first, skipping anywhere too close to a source
+    // that's already been picked
+    fn find_sources(&self) -> Vec<Cell> {
+        let mut candidates = Vec::<Cell>::new();
+        for x in 0..self.width {
+            for y in 0..self.height {
+                if self.heights[x][y] >= SOURCE_MIN_HEIGHT {
+                    candidates.push((x, y));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| {
+            self.get_height(*b).partial_cmp(&self.get_height(*a)).unwrap().then(a.cmp(b))
+        });
+
+        let max_rivers = (self.width * self.height / REGIONS_PER_RIVER).max(1);
+        let mut sources = Vec::<Cell>::new();
+        for cell in candidates {
+            if sources.len() >= max_rivers {
+                break;
+            }
+
+            let spaced = sources.iter().all(|s| {
+                let dx = (s.0 as i32 - cell.0 as i32).abs();
+                let dy = (s.1 as i32 - cell.1 as i32).abs();
+                dx.max(dy) >= SOURCE_SPACING
+            });
+            if spaced {
+                sources.push(cell);
+            }
+        }
+        sources
+    }
+
+    fn trace_river(&mut self, source: Cell) {
+        let mut visited = HashSet::<Cell>::new();
+        let mut cell = source;
+
+        for _ in 0..self.width * self.height {
+            visited.insert(cell);
+            if self.is_sea(cell) {
+                return;
+            }
+
+            // water reaching a lake carries on from wherever the lake drains
+            if self.lakes[cell.0][cell.1].is_some() {
+                match self.lake_exits[cell.0][cell.1] {
+                    Some(exit) if exit != cell => {
+                        cell = exit;
+                        continue;
+                    }
+                    Some(_) => {}
+                    None => return,
+                }
+            }
+
+            // joining a river that's already there
+            if let Some(next) = self.downstream[cell.0][cell.1] {
+                self.flow[cell.0][cell.1] += 1;
+                cell = next;
+                continue;
+            }
+
+            let lowest = self.neighbors(cell)
+                .into_iter()
+                .filter(|n| !visited.contains(n))
+                .min_by(|a, b| {
+                    self.get_height(*a).partial_cmp(&self.get_height(*b)).unwrap()
+                });
+            match lowest {
+                Some(next) if self.get_height(next) < self.get_height(cell) => {
+                    self.downstream[cell.0][cell.1] = Some(next);
+                }
+                _ => {
+                    if !self.fill_lake(cell, &mut visited) {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    // floods the basin around a cell, lowest ground first, until the water
+    // finds a way out; false if the basin got too big before it did
+    fn fill_lake(&mut self, bottom: Cell, visited: &mut HashSet<Cell>) -> bool {
+        let mut lake = vec![bottom];
+        let mut level = self.get_height(bottom);
+        let mut outlet: Option<(Cell, Cell)> = None;
+
+        while outlet.is_none() && lake.len() < MAX_LAKE_SIZE {
+            // the lowest ground around the lake, and the lake cell beside it
+            let mut shore: Option<(Cell, Cell)> = None;
+            for &cell in lake.iter() {
+                for n in self.neighbors(cell) {
+                    // never back up the river that's filling the lake
+                    if lake.contains(&n) || visited.contains(&n) {
+                        continue;
+                    }
+                    let lower = shore.map_or(true, |(_, s)| self.get_height(n) < self.get_height(s));
+                    if lower {
+                        shore = Some((cell, n));
+                    }
+                }
+            }
+
+            match shore {
+                Some((from, to)) => {
+                    if self.get_height(to) < level {
+                        outlet = Some((from, to));
+                    } else {
+                        level = level.max(self.get_height(to));
+                        lake.push(to);
+                    }
+                }
+                None => break,
+            }
+        }
+
+        let exit = outlet.map(|(from, _)| from);
+        for &(x, y) in lake.iter() {
+            self.lakes[x][y] = Some(level);
+            self.lake_exits[x][y] = exit;
+            visited.insert((x, y));
+        }
+
+        match outlet {
+            Some((from, to)) => {
+                self.downstream[from.0][from.1] = Some(to);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn into_hydrology(self) -> Hydrology {
+        let mut rivers = vec![vec![Vec::<RiverSegment>::new(); self.height]; self.width];
+        for x in 0..self.width {
+            for y in 0..self.height {
+                let flow = self.flow[x][y];
+                if let Some((nx, ny)) = self.downstream[x][y] {
+                    if flow == 0 {
+                        continue;
+                    }
+
+                    let dx = nx as i32 - x as i32;
+                    let dy = ny as i32 - y as i32;
+                    rivers[x][y].push(RiverSegment {
+                        direction: (dx, dy),
+                        flow: flow,
+                    });
+                    rivers[nx][ny].push(RiverSegment {
+                        direction: (-dx, -dy),
+                        flow: flow,
+                    });
+                }
+            }
+        }
+
+        Hydrology {
+            rivers: rivers,
+            lakes: self.lakes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{trace, RiverSegment};
+
+    const SEA: f32 = 0.0;
+
+    // a slope falling from east to west into the sea along x = 0
+    fn slope(width: usize, height: usize) -> Vec<Vec<f32>> {
+        (0..width)
+            .map(|x| {
+                (0..height)
+                    .map(|y| if x == 0 { -0.5 } else { x as f32 * 0.1 + y as f32 * 0.001 })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rivers_run_downhill_to_the_sea() {
+        let hydrology = trace(&slope(8, 3), SEA);
+
+        //the source is the highest point, and each step heads west
+        assert_eq!(
+            hydrology.rivers[7][2],
+            vec![RiverSegment { direction: (-1, -1), flow: 1 }]
+        );
+        let mouth = &hydrology.rivers[1];
+        assert!(mouth.iter().any(|r| r.iter().any(|s| s.direction.0 == -1)));
+        assert!(hydrology.rivers[0].iter().any(|r| !r.is_empty()));
+        assert!(hydrology.lakes.iter().all(|c| c.iter().all(|l| l.is_none())));
+
+        //both halves of every segment line up across the border
+        for x in 0..8 {
+            for y in 0..3 {
+                for segment in hydrology.rivers[x][y].iter() {
+                    let nx = (x as i32 + segment.direction.0) as usize;
+                    let ny = (y as i32 + segment.direction.1) as usize;
+                    let back = RiverSegment {
+                        direction: (-segment.direction.0, -segment.direction.1),
+                        flow: segment.flow,
+                    };
+                    assert!(hydrology.rivers[nx][ny].contains(&back));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn basins_fill_into_lakes_that_drain_onward() {
+        let mut heights = slope(8, 3);
+        //a dip part way down the slope, with a lower rim on the west side
+        heights[4][1] = 0.2;
+        heights[3][1] = 0.25;
+
+        let hydrology = trace(&heights, SEA);
+        assert!(hydrology.lakes[4][1].is_some());
+        assert!(hydrology.lakes[7][2].is_none());
+
+        //the river still makes it to the sea
+        assert!(hydrology.rivers[0].iter().any(|r| !r.is_empty()));
+    }
+}
@@ -1,18 +1,28 @@
-use std::io::{BufReader, Write, Read};
+use std::io::{BufReader, Read};
 use std::fs::{DirBuilder, File};
+use std::path::Path;
 
 use uuid::Uuid;
 use bincode::{serialize, deserialize, Infinite};
-use super::regions_generator::RegionsGenerator;
-
+use super::biomes;
+use super::seed;
+use super::hydrology;
+use super::hydrology::RiverSegment;
+use super::pool;
+use super::regions_generator::{RegionsGenerator, Climate};
+use utility::files;
+use utility::i18n;
+
+pub const MAPS_DIR: &'static str = "maps";
 const REGION_DEPTH: u32 = 16;
-
-lazy_static! {
-    static ref REGION_NAME_ARID: String = String::from("Arid");
-    static ref REGION_NAME_GRASSLAND: String = String::from("Grassland");
-    static ref REGION_NAME_OCEAN: String = String::from("Oceanic");
-    static ref REGION_NAME_ROCKY: String = String::from("Rocky");
-}
+// rivers are cut this many tiles into the ground, and are wider the more
+// rivers have joined them
+const RIVER_DEPTH: u32 = 2;
+const RIVER_HALF_WIDTH: f32 = 1.0;
+const RIVER_WIDTH_PER_FLOW: f32 = 0.5;
+const RIVER_MAX_FLOW_WIDTH: u32 = 4;
+// how deep the bowl a lake sits in is at its middle
+const LAKE_DEPTH: f32 = 4.0;
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct Map {
@@ -21,15 +31,29 @@ pub struct Map {
     height: u32,
     region_size: u32,
     regions: Vec<Vec<Region>>,
-    seed: usize,
+    seed: u64,
+    // the seed::GENERATOR_VERSION the map was made with
+    generator_version: u32,
+    // the hash of the biomes the map was made with; unsaved regions are
+    // generated from them, so they have to be the same ones
+    biomes_hash: u64,
+    // the directory that maps are saved under
+    #[serde(skip, default = "maps_root")]
+    root: String,
 }
 
+// the id of one of the biomes in resources/biomes.json
 #[derive(Clone, Serialize, Deserialize, PartialEq, Debug, Eq, Hash)]
-pub enum BiomeType {
-    Arid,
-    Grassland,
-    Ocean,
-    Rocky,
+pub struct BiomeType(String);
+
+impl BiomeType {
+    pub fn new(id: &str) -> BiomeType {
+        BiomeType(String::from(id))
+    }
+
+    pub fn id(&self) -> &str {
+        &self.0
+    }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -38,10 +62,22 @@ pub struct Region {
     width: u32,
     height: u32,
     biome: BiomeType,
+    climate: Climate,
+    rivers: Vec<RiverSegment>,
+    lake: bool,
+    // whether the tiles have ever been saved, and so have a file to load from
+    saved: bool,
+    // tiles are saved to a file of their own, and only kept in memory while
+    // the region is loaded
+    #[serde(skip)]
     tiles: Vec<Vec<Vec<Tile>>>,
+    // whether the tiles have changed since they were last saved
+    #[serde(skip)]
+    dirty: bool,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
 pub enum TileType {
     Air,
     Grass,
@@ -57,13 +93,12 @@ struct Tile {
     pub tile_type: TileType,
 }
 
-pub fn get_biome_name(biome: &BiomeType) -> &String {
-    match biome {
-        &BiomeType::Arid => &*REGION_NAME_ARID,
-        &BiomeType::Grassland => &*REGION_NAME_GRASSLAND,
-        &BiomeType::Ocean => &*REGION_NAME_OCEAN,
-        &BiomeType::Rocky => &*REGION_NAME_ROCKY,
-    }
+fn maps_root() -> String {
+    String::from(MAPS_DIR)
+}
+
+pub fn get_biome_name(biome: &BiomeType) -> String {
+    i18n::tr(&biomes::get(biome).name)
 }
 
 impl Map {
@@ -75,36 +110,91 @@ impl Map {
             region_size: region_size,
             regions: Vec::<Vec<Region>>::new(),
             seed: 0,
+            generator_version: seed::GENERATOR_VERSION,
+            biomes_hash: biomes::registry().get_hash(),
+            root: maps_root(),
         }
     }
 
-    pub fn set_seed(&mut self, seed: usize) {
+    pub fn set_seed(&mut self, seed: u64) {
         self.seed = seed;
+        self.generator_version = seed::GENERATOR_VERSION;
+        self.biomes_hash = biomes::registry().get_hash();
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn get_generator_version(&self) -> u32 {
+        self.generator_version
     }
 
-    pub fn generate_regions<F>(&mut self, progress_callback: F)
+    // reports how far along it is, from 0 to 100, as columns of regions are
+    // finished; if the callback returns false generation stops, leaving the
+    // map without regions
+    pub fn generate_regions<F>(&mut self, progress_callback: F) -> bool
     where
-        F: Fn(i32),
+        F: FnMut(i32) -> bool,
     {
-        self.regions.clear();
+        self.generate_regions_on(pool::worker_count(), progress_callback)
+    }
 
-        let region_gen = RegionsGenerator::new(self.seed);
-        let region_count = (self.width * self.height) as f32;
-        let mut current_index = 0;
+    // columns are handed out to the workers as they become free; every region
+    // only depends on the seed and where it is, so the map comes out the same
+    // however many workers there are and whichever of them made each column
+    fn generate_regions_on<F>(&mut self, workers: usize, mut progress_callback: F) -> bool
+    where
+        F: FnMut(i32) -> bool,
+    {
+        self.regions.clear();
 
-        for x in 0..self.width {
-            let mut column = Vec::<Region>::new();
-            for y in 0..self.height {
-                let biome = region_gen.get_biome_at_point(x, y);
+        let region_gen = RegionsGenerator::new(self.seed, self.height);
+        // tracing the rivers counts as one more step after the regions
+        let step_count = (self.width * self.height + 1) as f32;
+        let mut current_step = 0;
+
+        let mut columns: Vec<Option<(Vec<Region>, Vec<f32>)>> =
+            (0..self.width).map(|_| None).collect();
+        let (map_height, region_size) = (self.height, self.region_size);
+        let finished = pool::run(
+            self.width as usize,
+            workers,
+            |x| generate_column(&region_gen, x as u32, map_height, region_size),
+            |x, column| {
+                current_step += column.0.len() as u32;
+                columns[x] = Some(column);
+
+                let progress = (current_step as f32 / step_count * 100.0) as i32;
+                progress_callback(progress)
+            },
+        );
+        if !finished {
+            return false;
+        }
 
-                column.push(Region::new(self.region_size, biome));
+        let mut heights = Vec::<Vec<f32>>::new();
+        for column in columns.into_iter() {
+            let (regions, column_heights) = column.unwrap();
+            self.regions.push(regions);
+            heights.push(column_heights);
+        }
 
-                current_index += 1;
-                let progress = ((current_index as f32 / region_count) as i32) * 100;
-                progress_callback(progress);
+        let water = hydrology::trace(&heights, region_gen.get_sea_level());
+        for (x, column) in self.regions.iter_mut().enumerate() {
+            for (y, region) in column.iter_mut().enumerate() {
+                region.rivers = water.rivers[x][y].clone();
+                region.lake = water.lakes[x][y].is_some();
             }
-            self.regions.push(column);
         }
+
+        progress_callback(100);
+        true
+    }
+
+    // false until generate_regions has run to the end
+    pub fn has_regions(&self) -> bool {
+        !self.regions.is_empty()
     }
 
     pub fn get_width(&self) -> u32 {
@@ -115,39 +205,203 @@ impl Map {
         self.height
     }
 
-    pub fn save(&mut self) {
-        let path = format!("maps/{}/", self.id);
-        let _ = DirBuilder::new()
-            .recursive(true)
-            .create(path.clone())
-            .unwrap();
+    // somewhere other than MAPS_DIR to save the map under
+    pub fn set_root(&mut self, root: &str) {
+        self.root = String::from(root);
+    }
+
+    pub fn get_directory(&self) -> String {
+        format!("{}/{}/", self.root, self.id)
+    }
+
+    // writes out the tiles of every loaded region that's changed, then the
+    // map; loaded regions stay loaded
+    pub fn save(&mut self) -> Result<(), String> {
+        let path = self.get_directory();
+        create_directory(&path)?;
 
-        for mut c in self.regions.iter_mut() {
-            for mut r in c.iter_mut() {
-                r.unload(path.clone());
+        for c in self.regions.iter_mut() {
+            for r in c.iter_mut() {
+                if r.dirty {
+                    r.save_tiles(path.clone())?;
+                }
             }
         }
 
         let fname = format!("{}{}.map", path, self.id);
-        let mut file = File::create(fname).unwrap();
-        let encoded = serialize(&self, Infinite).unwrap();
-        file.write_all(encoded.as_slice()).unwrap();
+        let encoded = serialize(&self, Infinite).map_err(|e| format!("{}: {}", fname, e))?;
+        files::write_atomically(Path::new(&fname), &encoded)
+    }
+
+    // replaces this map with a saved one, as long as it's whole; otherwise
+    // this map is left as it was
+    pub fn load(&mut self, id: String) -> Result<(), String> {
+        let path = format!("{}/{}/", self.root, id);
+        let fname = format!("{}{}.map", path, id);
+        let buffer = read_file(&fname)?;
+        let mut map: Map = deserialize(&buffer).map_err(|e| format!("{}: {}", fname, e))?;
+        map.root = self.root.clone();
+        map.validate().map_err(|e| format!("{}: {}", fname, e))?;
+
+        *self = map;
+        Ok(())
+    }
+
+    // the map was made the way new regions would be made now, every region
+    // it should have is there, and every region that's been saved still has
+    // its tiles file
+    fn validate(&self) -> Result<(), String> {
+        if self.generator_version != seed::GENERATOR_VERSION {
+            return Err(format!(
+                "Made with version {} of the map generator, not {}",
+                self.generator_version,
+                seed::GENERATOR_VERSION
+            ));
+        }
+        if self.biomes_hash != biomes::registry().get_hash() {
+            return Err(format!(
+                "Made with different biomes, {} has changed since",
+                biomes::BIOMES_FILE
+            ));
+        }
+
+        if self.regions.len() != self.width as usize ||
+            self.regions.iter().any(|c| c.len() != self.height as usize)
+        {
+            return Err(format!(
+                "Expected {}x{} regions",
+                self.width,
+                self.height
+            ));
+        }
+
+        let path = self.get_directory();
+        for (x, column) in self.regions.iter().enumerate() {
+            for (y, region) in column.iter().enumerate() {
+                if region.saved && !Path::new(&region.get_file_name(&path)).exists() {
+                    return Err(format!(
+                        "Region ({}, {}) is missing its tiles, {}",
+                        x,
+                        y,
+                        region.get_file_name(&path)
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // reads the region's tiles in if they aren't already, or makes them if
+    // they've never been saved
+    pub fn load_region(&mut self, x: u32, y: u32) -> Result<&mut Region, String> {
+        self.check_bounds(x, y);
+        let path = self.get_directory();
+        let region_gen = RegionsGenerator::new(self.seed, self.height);
+        let r = &mut self.regions[x as usize][y as usize];
+        if !r.has_tiles() {
+            r.load_or_generate_tiles(path, &region_gen, x, y)?;
+        }
+        Ok(r)
     }
 
-    pub fn load(&mut self, id: String) {
-        let path = format!("maps/{}/", id);
-        let file = File::open(format!("{}{}.map", path.clone(), id)).unwrap();
-        let mut buffer = Vec::<u8>::new();
-        let _ = BufReader::new(file).read_to_end(&mut buffer).unwrap();
-        *self = deserialize(&buffer).unwrap();
+    // loads (or makes) the tiles of every listed region that isn't loaded
+    // yet, spread over the worker pool
+    pub fn load_regions(&mut self, offsets: &[(u32, u32)]) -> Result<(), String> {
+        self.load_regions_on(pool::worker_count(), offsets)
     }
 
-    pub fn load_region(&mut self, x: u32, y: u32) -> &mut Region {
+    fn load_regions_on(&mut self, workers: usize, offsets: &[(u32, u32)]) -> Result<(), String> {
+        let unloaded: Vec<(u32, u32)> = offsets
+            .iter()
+            .cloned()
+            .filter(|&(x, y)| !self.is_region_loaded(x, y))
+            .collect();
+
+        let mut loaded = Vec::<((u32, u32), Region)>::new();
+        let mut failure: Option<String> = None;
+        self.load_detached_regions(workers, &unloaded, |offset, region| match region {
+            Ok(region) => {
+                loaded.push((offset, region));
+                true
+            }
+            Err(why) => {
+                failure = Some(why);
+                false
+            }
+        });
+
+        for ((x, y), region) in loaded.into_iter() {
+            self.attach_tiles(x, y, region);
+        }
+        match failure {
+            Some(why) => Err(why),
+            None => Ok(()),
+        }
+    }
+
+    // loads or makes the tiles of detached copies of the listed regions on
+    // the worker pool, handing each to `done` as it's ready; every region's
+    // tiles only depend on the seed and where it is, so they're the same
+    // whichever worker makes them
+    fn load_detached_regions<D>(&self, workers: usize, offsets: &[(u32, u32)], done: D) -> bool
+    where
+        D: FnMut((u32, u32), Result<Region, String>) -> bool,
+    {
+        let mut done = done;
+        let region_gen = RegionsGenerator::new(self.seed, self.height);
+        let path = self.get_directory();
+        pool::run(
+            offsets.len(),
+            workers,
+            |i| {
+                let (x, y) = offsets[i];
+                let mut region = self.detach_region(x, y);
+                region.load_or_generate_tiles(path.clone(), &region_gen, x, y).map(|_| region)
+            },
+            |i, region| done(offsets[i], region),
+        )
+    }
+
+    // saves the region's tiles if they've changed, then lets them go; if they
+    // can't be saved they're kept
+    pub fn unload_region(&mut self, x: u32, y: u32) -> Result<(), String> {
+        self.check_bounds(x, y);
+        let path = self.get_directory();
+        self.regions[x as usize][y as usize].unload(path)
+    }
+
+    pub fn is_region_loaded(&self, x: u32, y: u32) -> bool {
+        self.check_bounds(x, y);
+        self.regions[x as usize][y as usize].has_tiles()
+    }
+
+    // a copy of the region without its tiles, for loading them elsewhere; see
+    // attach_tiles
+    pub fn detach_region(&self, x: u32, y: u32) -> Region {
+        self.check_bounds(x, y);
+        self.regions[x as usize][y as usize].detached()
+    }
+
+    // takes the tiles of a detached copy of a region that's had them loaded
+    pub fn attach_tiles(&mut self, x: u32, y: u32, loaded: Region) {
+        self.check_bounds(x, y);
+        let r = &mut self.regions[x as usize][y as usize];
+        r.tiles = loaded.tiles;
+        r.dirty = loaded.dirty;
+        r.saved = r.saved || loaded.saved;
+    }
+
+    fn check_bounds(&self, x: u32, y: u32) {
+        if x >= self.width || y >= self.height {
+            panic!("Region offset at ({},{}) is outside of map bounds!", x, y);
+        }
+    }
+
+    pub fn generate_region_tiles(&mut self, x: u32, y: u32) {
         if x < self.width && y < self.height {
-            let path = format!("maps/{}/", self.id);
-            let r = &mut self.regions[x as usize][y as usize];
-            r.load_tiles(path.clone());
-            return r;
+            let region_gen = RegionsGenerator::new(self.seed, self.height);
+            self.regions[x as usize][y as usize].generate_tiles(&region_gen, x, y);
+            return;
         }
         panic!("Region offset at ({},{}) is outside of map bounds!", x, y);
     }
@@ -158,6 +412,27 @@ impl Map {
         }
         panic!("Region offset at ({},{}) is outside of map bounds!", x, y);
     }
+
+    pub fn get_rivers_at_offset(&self, x: u32, y: u32) -> Vec<RiverSegment> {
+        if x < self.width && y < self.height {
+            return self.regions[x as usize][y as usize].rivers.clone();
+        }
+        panic!("Region offset at ({},{}) is outside of map bounds!", x, y);
+    }
+
+    pub fn has_lake_at_offset(&self, x: u32, y: u32) -> bool {
+        if x < self.width && y < self.height {
+            return self.regions[x as usize][y as usize].lake;
+        }
+        panic!("Region offset at ({},{}) is outside of map bounds!", x, y);
+    }
+
+    pub fn get_climate_at_offset(&self, x: u32, y: u32) -> Climate {
+        if x < self.width && y < self.height {
+            return self.regions[x as usize][y as usize].climate;
+        }
+        panic!("Region offset at ({},{}) is outside of map bounds!", x, y);
+    }
 }
 
 impl Region {
@@ -167,27 +442,109 @@ impl Region {
             width: size,
             height: size,
             biome: biome,
+            climate: Climate::default(),
+            rivers: Vec::<RiverSegment>::new(),
+            lake: false,
+            saved: false,
+            tiles: Vec::<Vec<Vec<Tile>>>::new(),
+            dirty: false,
+        }
+    }
+
+    // a copy of the region without its tiles
+    pub fn detached(&self) -> Region {
+        Region {
+            id: self.id,
+            width: self.width,
+            height: self.height,
+            biome: self.biome.clone(),
+            climate: self.climate,
+            rivers: self.rivers.clone(),
+            lake: self.lake,
+            saved: self.saved,
             tiles: Vec::<Vec<Vec<Tile>>>::new(),
+            dirty: false,
         }
     }
 
-    pub fn generate_tiles(&mut self) {
-        for _ in 0..self.width {
+    pub fn has_tiles(&self) -> bool {
+        !self.tiles.is_empty()
+    }
+
+    // for anything that changes the tiles, so they're saved before they're
+    // unloaded
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn get_tile_type(&self, x: u32, y: u32, z: u32) -> TileType {
+        self.tiles[x as usize][y as usize][z as usize].tile_type
+    }
+
+    pub fn set_tile(&mut self, x: u32, y: u32, z: u32, tile_type: TileType, solid: bool) {
+        self.tiles[x as usize][y as usize][z as usize] = Tile {
+            solid: solid,
+            tile_type: tile_type,
+        };
+        self.mark_dirty();
+    }
+
+    // fills the region with columns of tiles, z counting up from the bottom;
+    // the ground's height and what it's made of come from noise and biomes
+    // blended over the whole map, so they line up with the neighboring regions
+    pub fn generate_tiles(&mut self, region_gen: &RegionsGenerator, region_x: u32, region_y: u32) {
+        self.tiles.clear();
+        let sea_level = level_for_height(region_gen.get_sea_level());
+        let neighborhood = region_gen.get_neighborhood(region_x, region_y);
+
+        // a lake's surface is level with the ground at its middle
+        let lake_level = if self.lake {
+            let middle = region_gen.get_terrain_at_tile(
+                region_x * self.width + self.width / 2,
+                region_y * self.height + self.height / 2,
+                self.width,
+                &neighborhood,
+            );
+            level_for_height(middle.height)
+        } else {
+            0
+        };
+
+        for x in 0..self.width {
             let mut column = Vec::<Vec<Tile>>::new();
-            for _ in 0..self.height {
-                let mut width = Vec::<Tile>::new();
+            for y in 0..self.height {
+                let terrain = region_gen.get_terrain_at_tile(
+                    region_x * self.width + x,
+                    region_y * self.height + y,
+                    self.width,
+                    &neighborhood,
+                );
+                let natural = level_for_height(terrain.height);
+                let (surface, water_level) = self.carve_water(x, y, natural, lake_level);
+                let underwater = surface < sea_level || water_level > surface;
 
+                let mut width = Vec::<Tile>::new();
                 for z in 0..REGION_DEPTH {
-                    // TODO: Replace this with map generation logic
-                    if z > REGION_DEPTH / 2 {
+                    if z == 0 {
+                        // the bottom of the map is always stone
+                        width.push(Tile {
+                            solid: true,
+                            tile_type: TileType::Stone,
+                        });
+                    } else if z <= surface {
+                        width.push(Tile {
+                            solid: true,
+                            tile_type: ground_type(&terrain.biome, surface - z, underwater),
+                        });
+                    } else if z <= sea_level || z <= water_level {
                         width.push(Tile {
                             solid: false,
-                            tile_type: TileType::Air,
+                            tile_type: TileType::Water,
                         });
                     } else {
                         width.push(Tile {
-                            solid: true,
-                            tile_type: TileType::Stone,
+                            solid: false,
+                            tile_type: TileType::Air,
                         });
                     }
                 }
@@ -196,30 +553,459 @@ impl Region {
             }
             self.tiles.push(column);
         }
+        self.dirty = true;
+    }
+
+    // lowers the ground under the region's rivers and lake, returning the new
+    // height of the ground and how high the water over it comes
+    fn carve_water(&self, x: u32, y: u32, surface: u32, lake_level: u32) -> (u32, u32) {
+        let size = self.width as f32;
+        let middle = size / 2.0;
+        let point = (x as f32 + 0.5, y as f32 + 0.5);
+        let mut ground = surface;
+        let mut water = 0;
+
+        for segment in self.rivers.iter() {
+            let end = (
+                middle + segment.direction.0 as f32 * middle,
+                middle + segment.direction.1 as f32 * middle,
+            );
+            let half_width = RIVER_HALF_WIDTH +
+                segment.flow.min(RIVER_MAX_FLOW_WIDTH) as f32 * RIVER_WIDTH_PER_FLOW;
+            if distance_to_segment(point, (middle, middle), end) < half_width {
+                let bed = surface.saturating_sub(RIVER_DEPTH).max(1);
+                ground = ground.min(bed);
+                water = water.max((bed + 1).max(surface - 1));
+            }
+        }
+
+        if self.lake {
+            let dx = point.0 - middle;
+            let dy = point.1 - middle;
+            let falloff = 1.0 - (dx * dx + dy * dy).sqrt() / middle;
+            if falloff > 0.0 {
+                let depth = (falloff * LAKE_DEPTH).round() as u32;
+                let bed = surface.saturating_sub(depth).max(1);
+                ground = ground.min(bed);
+                water = water.max(lake_level);
+            }
+        }
+
+        (ground, water)
+    }
+
+    fn get_file_name(&self, dir: &str) -> String {
+        format!("{}{}.region", dir, self.id)
+    }
+
+    pub fn load_tiles(&mut self, dir: String) -> Result<(), String> {
+        let fname = self.get_file_name(&dir);
+        let buffer = read_file(&fname)?;
+        self.tiles = deserialize(&buffer).map_err(|e| format!("{}: {}", fname, e))?;
+        self.dirty = false;
+        Ok(())
     }
 
-    pub fn load_tiles(&mut self, dir: String) {
-        let file = File::open(format!("{}{}.region", dir, self.id)).unwrap();
-        let mut buffer = Vec::<u8>::new();
-        let _ = BufReader::new(file).read_to_end(&mut buffer).unwrap();
-        self.tiles = deserialize(&buffer).unwrap();
+    pub fn load_or_generate_tiles(
+        &mut self,
+        dir: String,
+        region_gen: &RegionsGenerator,
+        region_x: u32,
+        region_y: u32,
+    ) -> Result<(), String> {
+        if self.saved {
+            self.load_tiles(dir)
+        } else {
+            self.generate_tiles(region_gen, region_x, region_y);
+            Ok(())
+        }
     }
 
-    pub fn unload(&mut self, dir: String) {
-        if self.tiles.len() > 0 {
-            self.save_tiles(dir);
+    pub fn unload(&mut self, dir: String) -> Result<(), String> {
+        if self.dirty {
+            self.save_tiles(dir)?;
         }
         self.dispose_tiles();
+        Ok(())
     }
 
-    fn save_tiles(&self, dir: String) {
-        let fname = format!("{}{}.region", dir, self.id);
-        let mut file = File::create(fname).unwrap();
-        let encoded = serialize(&self.tiles, Infinite).unwrap();
-        file.write_all(encoded.as_slice()).unwrap();
+    fn save_tiles(&mut self, dir: String) -> Result<(), String> {
+        create_directory(&dir)?;
+        let fname = self.get_file_name(&dir);
+        let encoded = serialize(&self.tiles, Infinite).map_err(|e| format!("{}: {}", fname, e))?;
+        files::write_atomically(Path::new(&fname), &encoded)?;
+        self.dirty = false;
+        self.saved = true;
+        Ok(())
     }
 
     pub fn dispose_tiles(&mut self) {
         self.tiles.clear();
+        self.dirty = false;
+    }
+}
+
+fn create_directory(path: &str) -> Result<(), String> {
+    DirBuilder::new().recursive(true).create(path).map_err(
+        |e| format!("{}: {}", path, e),
+    )
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut buffer = Vec::<u8>::new();
+    BufReader::new(file).read_to_end(&mut buffer).map_err(
+        |e| format!("{}: {}", path, e),
+    )?;
+    Ok(buffer)
+}
+
+// a column of regions without their rivers, which need the whole map, along
+// with the height of the ground at each
+fn generate_column(
+    region_gen: &RegionsGenerator,
+    x: u32,
+    map_height: u32,
+    region_size: u32,
+) -> (Vec<Region>, Vec<f32>) {
+    let mut column = Vec::<Region>::new();
+    let mut heights = Vec::<f32>::new();
+    for y in 0..map_height {
+        let mut region = Region::new(region_size, region_gen.get_biome_at_point(x, y));
+        region.climate = region_gen.get_climate_at_point(x, y);
+        column.push(region);
+        heights.push(region_gen.get_height_at_point(x, y));
+    }
+    (column, heights)
+}
+
+// the z of the top solid tile for a noise height, which runs from about -1 to
+// 1; there's always at least one layer of ground and one of air
+fn level_for_height(height: f32) -> u32 {
+    let t = ((height + 1.0) / 2.0).max(0.0).min(1.0);
+    1 + (t * (REGION_DEPTH - 3) as f32).round() as u32
+}
+
+fn distance_to_segment(point: (f32, f32), start: (f32, f32), end: (f32, f32)) -> f32 {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length = dx * dx + dy * dy;
+    let t = if length > 0.0 {
+        (((point.0 - start.0) * dx + (point.1 - start.1) * dy) / length).max(0.0).min(1.0)
+    } else {
+        0.0
+    };
+    let (cx, cy) = (start.0 + t * dx - point.0, start.1 + t * dy - point.1);
+    (cx * cx + cy * cy).sqrt()
+}
+
+// what the ground is made of the given number of tiles below the surface
+fn ground_type(biome: &BiomeType, depth: u32, underwater: bool) -> TileType {
+    let materials = &biomes::get(biome).materials;
+    if depth >= materials.depth {
+        TileType::Stone
+    } else if underwater {
+        materials.underwater
+    } else if depth == 0 {
+        materials.surface
+    } else {
+        materials.subsurface
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::time::Instant;
+
+    use super::{Map, Region, BiomeType, TileType, RiverSegment, REGION_DEPTH};
+    use super::super::pool;
+    use super::super::regions_generator::RegionsGenerator;
+
+    const SIZE: u32 = 16;
+
+    // everything about each region apart from its id, which is random
+    fn describe_regions(map: &Map) -> Vec<String> {
+        map.regions
+            .iter()
+            .flat_map(|column| column.iter())
+            .map(|r| format!("{:?} {:?} {:?} {}", r.biome, r.climate, r.rivers, r.lake))
+            .collect()
+    }
+
+    fn surface(region: &Region, x: usize, y: usize) -> u32 {
+        let column = &region.tiles[x][y];
+        (0..REGION_DEPTH)
+            .rev()
+            .find(|&z| column[z as usize].solid)
+            .unwrap()
+    }
+
+    #[test]
+    fn region_edges_match_their_neighbors() {
+        let region_gen = RegionsGenerator::new(1234, 36);
+
+        // a pair of neighboring regions with different biomes
+        let rx = (0..64)
+            .find(|&x| region_gen.get_biome_at_point(x, 5) != region_gen.get_biome_at_point(x + 1, 5))
+            .unwrap();
+        let mut left = Region::new(SIZE, region_gen.get_biome_at_point(rx, 5));
+        let mut right = Region::new(SIZE, region_gen.get_biome_at_point(rx + 1, 5));
+        left.generate_tiles(&region_gen, rx, 5);
+        right.generate_tiles(&region_gen, rx + 1, 5);
+
+        let last = (SIZE - 1) as usize;
+        for y in 0..SIZE as usize {
+            let step = surface(&left, last, y) as i32 - surface(&right, 0, y) as i32;
+            assert!(step.abs() <= 1, "Step of {} at row {}", step, y);
+
+            //the blend carries straight on across the border, and reaches
+            //into the left region
+            let tile_y = 5 * SIZE + y as u32;
+            let inside = region_gen.get_biome_weights_at_tile(rx * SIZE + last as u32, tile_y, SIZE);
+            let outside = region_gen.get_biome_weights_at_tile((rx + 1) * SIZE, tile_y, SIZE);
+            for &(ref biome, weight) in inside.iter() {
+                let other = outside.iter().find(|w| w.0 == *biome).map_or(0.0, |w| w.1);
+                assert!((weight - other).abs() < 0.2, "{:?} against {:?}", inside, outside);
+            }
+            assert!(inside.iter().any(|w| w.0 == right.biome), "{:?}", inside);
+        }
+    }
+
+    #[test]
+    fn rivers_are_cut_into_the_ground_on_both_sides_of_a_border() {
+        let region_gen = RegionsGenerator::new(1234, 36);
+        let mut left = Region::new(SIZE, BiomeType::new("grassland"));
+        let mut right = Region::new(SIZE, BiomeType::new("grassland"));
+        left.rivers.push(RiverSegment { direction: (1, 0), flow: 1 });
+        right.rivers.push(RiverSegment { direction: (-1, 0), flow: 1 });
+
+        // somewhere well above the sea
+        let (rx, ry) = (0..64 * 36)
+            .map(|i| (i % 64, i / 64))
+            .find(|&(x, y)| {
+                region_gen.get_height_at_point(x, y) > 0.4 &&
+                    region_gen.get_height_at_point(x + 1, y) > 0.4
+            })
+            .unwrap();
+        left.generate_tiles(&region_gen, rx, ry);
+        right.generate_tiles(&region_gen, rx + 1, ry);
+
+        let has_water = |region: &Region, x: usize, y: usize| {
+            region.tiles[x][y].iter().any(|t| t.tile_type == TileType::Water)
+        };
+        let middle = (SIZE / 2) as usize;
+        for &x in [middle, (SIZE - 1) as usize].iter() {
+            assert!(has_water(&left, x, middle));
+        }
+        assert!(has_water(&right, 0, middle));
+        assert!(!has_water(&right, (SIZE - 1) as usize, 0));
+    }
+
+    #[test]
+    fn columns_are_ground_then_water_then_air() {
+        let region_gen = RegionsGenerator::new(42, 36);
+        for &(biome, x) in [("grassland", 0), ("ocean", 7)].iter() {
+            let mut region = Region::new(SIZE, BiomeType::new(biome));
+            region.generate_tiles(&region_gen, x, 2);
+            assert_eq!(region.tiles.len(), SIZE as usize);
+
+            for column in region.tiles.iter().flat_map(|c| c.iter()) {
+                assert_eq!(column.len(), REGION_DEPTH as usize);
+                assert_eq!(column[0].tile_type, TileType::Stone);
+                assert_eq!(column[REGION_DEPTH as usize - 1].tile_type, TileType::Air);
+
+                let top = column.iter().rposition(|t| t.solid).unwrap();
+                assert!(column[..top].iter().all(|t| t.solid));
+                assert!(column[top + 1..].iter().all(|t| !t.solid));
+                assert!(
+                    column[top + 1..]
+                        .windows(2)
+                        .all(|w| !(w[0].tile_type == TileType::Air && w[1].tile_type == TileType::Water))
+                );
+                //grass only grows on the surface, over soil
+                for z in 1..top {
+                    assert!(column[z].tile_type != TileType::Grass);
+                }
+                if column[top].tile_type == TileType::Grass {
+                    assert_eq!(column[top + 1].tile_type, TileType::Air);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn regions_are_the_same_however_many_workers_make_them() {
+        let generate = |workers: usize| {
+            let mut map = Map::new(40, 30, SIZE);
+            map.set_seed(77);
+            let mut progress = Vec::<i32>::new();
+            assert!(map.generate_regions_on(workers, |p| {
+                progress.push(p);
+                true
+            }));
+
+            //progress only ever goes up, and ends at 100
+            assert!(progress.windows(2).all(|w| w[0] <= w[1]));
+            assert_eq!(progress.last(), Some(&100));
+            describe_regions(&map)
+        };
+
+        let single = generate(1);
+        assert_eq!(single.len(), 40 * 30);
+        assert_eq!(generate(4), single);
+        assert_eq!(generate(64), single);
+    }
+
+    #[test]
+    fn stopping_generation_leaves_no_regions() {
+        let mut map = Map::new(40, 30, SIZE);
+        assert!(!map.generate_regions_on(4, |p| p < 50));
+        assert!(!map.has_regions());
+    }
+
+    #[test]
+    fn tiles_are_the_same_however_many_workers_make_them() {
+        let generate = |workers: usize| {
+            let mut map = Map::new(4, 3, SIZE);
+            map.set_seed(77);
+            map.generate_regions(|_| true);
+            let offsets: Vec<(u32, u32)> = (0..12).map(|i| (i % 4, i / 4)).collect();
+            map.load_regions_on(workers, &offsets).unwrap();
+            map.regions
+                .iter()
+                .flat_map(|column| column.iter())
+                .map(|r| format!("{:?}", r.tiles))
+                .collect::<Vec<String>>()
+        };
+
+        let single = generate(1);
+        assert!(single.iter().all(|tiles| tiles != "[]"));
+        assert_eq!(generate(4), single);
+        assert_eq!(generate(64), single);
+    }
+
+    // cargo test --release generation_benchmark -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn generation_benchmark() {
+        let mut worker_counts = vec![1];
+        if pool::worker_count() > 1 {
+            worker_counts.push(pool::worker_count());
+        }
+
+        for &size in [36, 72, 144, 288].iter() {
+            for &workers in worker_counts.iter() {
+                let mut map = Map::new(size, size, 32);
+                map.set_seed(1);
+                let start = Instant::now();
+                map.generate_regions_on(workers, |_| true);
+                println!(
+                    "{0}x{0} regions with {1} worker(s): {2:?}",
+                    size,
+                    workers,
+                    start.elapsed()
+                );
+            }
+        }
+
+        // the tiles of every region, thrown away as they're made since a
+        // whole map's worth wouldn't fit in memory; small regions keep the
+        // largest map to a reasonable time
+        for &size in [72, 144, 288].iter() {
+            let mut map = Map::new(size, size, 8);
+            map.set_seed(1);
+            map.generate_regions(|_| true);
+            let offsets: Vec<(u32, u32)> = (0..size * size).map(|i| (i % size, i / size)).collect();
+
+            for &workers in worker_counts.iter() {
+                let start = Instant::now();
+                map.load_detached_regions(workers, &offsets, |_, region| region.is_ok());
+                println!(
+                    "{0}x{0} tiles with {1} worker(s): {2:?}",
+                    size,
+                    workers,
+                    start.elapsed()
+                );
+            }
+        }
+    }
+
+    fn saved_map(name: &str) -> Map {
+        let mut map = Map::new(4, 3, SIZE);
+        map.set_seed(11);
+        map.set_root(env::temp_dir().join(name).to_str().unwrap());
+        map.generate_regions(|_| true);
+        map.load_region(1, 2).unwrap();
+        map.save().unwrap();
+        map
+    }
+
+    #[test]
+    fn maps_are_saved_whole_and_loaded_back() {
+        let map = saved_map("roguelike_map_round_trip");
+        let dir = map.get_directory();
+
+        //nothing is left half written
+        let mut files: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(files.len(), 2);
+        assert!(files.iter().all(|f| !f.ends_with(".tmp")));
+
+        let mut loaded = Map::new(1, 1, SIZE);
+        loaded.set_root(&map.root);
+        loaded.load(map.id.to_string()).unwrap();
+        assert_eq!(describe_regions(&loaded), describe_regions(&map));
+        assert!(!loaded.is_region_loaded(1, 2));
+        loaded.load_region(1, 2).unwrap();
+        assert_eq!(
+            format!("{:?}", loaded.regions[1][2].tiles),
+            format!("{:?}", map.regions[1][2].tiles)
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn broken_maps_are_errors_that_leave_the_map_alone() {
+        let map = saved_map("roguelike_map_broken");
+        let dir = map.get_directory();
+        let mut loaded = Map::new(1, 1, SIZE);
+        loaded.set_root(&map.root);
+
+        //a region file that's gone missing
+        let region_file = map.regions[1][2].get_file_name(&dir);
+        fs::remove_file(&region_file).unwrap();
+        let why = loaded.load(map.id.to_string()).unwrap_err();
+        assert!(why.contains("Region (1, 2)"), "{}", why);
+        assert_eq!(loaded.get_width(), 1);
+
+        //a map file that's been cut short
+        let map_file = format!("{}{}.map", dir, map.id);
+        let contents = fs::read(&map_file).unwrap();
+        fs::write(&map_file, &contents[..contents.len() / 2]).unwrap();
+        assert!(loaded.load(map.id.to_string()).is_err());
+
+        assert!(loaded.load(String::from("missing")).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn maps_made_with_other_biomes_are_errors() {
+        let mut map = saved_map("roguelike_map_other_biomes");
+        let dir = map.get_directory();
+        let mut loaded = Map::new(1, 1, SIZE);
+        loaded.set_root(&map.root);
+
+        map.biomes_hash ^= 1;
+        map.save().unwrap();
+        let why = loaded.load(map.id.to_string()).unwrap_err();
+        assert!(why.contains("different biomes"), "{}", why);
+        assert_eq!(loaded.get_width(), 1);
+
+        fs::remove_dir_all(dir).unwrap();
     }
 }
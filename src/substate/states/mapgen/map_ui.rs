@@ -1,20 +1,26 @@
 use ggez::Context;
-use ggez::graphics::{self, Color, Rect, DrawMode};
+use ggez::graphics::{self, Color, Rect, DrawMode, Point};
 
 use utility::ui;
+use utility::i18n;
+use utility::theme::Style;
 use substate::states::{StateInfo, StoredValue};
 use substate::states::mapgen::Map;
 use substate::states::mapgen::map::{self, BiomeType};
+use super::biomes;
+use super::hydrology::RiverSegment;
+use super::regions_generator::Climate;
 
-pub const DESCRIPTION_KEY: &'static str = "map_desc";
-const REGION_OUTLINE_WIDTH: f32 = 3.0;
+// the temperature range shown for climates, from coldest to hottest
+const TEMPERATURE_MIN_C: f32 = -20.0;
+const TEMPERATURE_MAX_C: f32 = 40.0;
+// lakes cover this much of a region's rectangle
+const LAKE_SCALE: f32 = 0.7;
+const RIVER_LINE_WIDTH: f32 = 1.0;
+const RIVER_WIDTH_PER_FLOW: f32 = 0.5;
+const RIVER_MAX_FLOW_WIDTH: u32 = 4;
 
-lazy_static! {
-    static ref REGION_COLOR_ARID: Color = Color::from((128, 98, 69));
-    static ref REGION_COLOR_GRASSLAND: Color = Color::from((64, 106, 57));
-    static ref REGION_COLOR_OCEAN: Color = Color::from((50, 61, 86));
-    static ref REGION_COLOR_ROCKY: Color = Color::from((26, 27, 31));
-}
+pub const DESCRIPTION_KEY: &'static str = "map_desc";
 
 pub struct MapUI {
     x: f32,
@@ -22,6 +28,9 @@ pub struct MapUI {
     width: f32,
     height: f32,
     biome_data: Vec<Vec<BiomeType>>,
+    climate_data: Vec<Vec<Climate>>,
+    river_data: Vec<Vec<Vec<RiverSegment>>>,
+    lake_data: Vec<Vec<bool>>,
     mouse_pos: (f32, f32),
     description: String,
     selection_pos: (f32, f32),
@@ -35,6 +44,9 @@ impl MapUI {
             width: 0.0,
             height: 0.0,
             biome_data: Vec::<Vec<BiomeType>>::new(),
+            climate_data: Vec::<Vec<Climate>>::new(),
+            river_data: Vec::<Vec<Vec<RiverSegment>>>::new(),
+            lake_data: Vec::<Vec<bool>>::new(),
             mouse_pos: (0.0, 0.0),
             description: String::new(),
             selection_pos: (0.0, 0.0),
@@ -43,12 +55,28 @@ impl MapUI {
 
     pub fn update(&mut self, map: &Map) {
         self.biome_data.clear();
+        self.climate_data.clear();
+        self.river_data.clear();
+        self.lake_data.clear();
+        if !map.has_regions() {
+            return;
+        }
+
         for x in 0..map.get_width() {
             let mut column = Vec::<BiomeType>::new();
+            let mut climates = Vec::<Climate>::new();
+            let mut rivers = Vec::<Vec<RiverSegment>>::new();
+            let mut lakes = Vec::<bool>::new();
             for y in 0..map.get_height() {
                 column.push(map.get_biome_at_offset(x, y));
+                climates.push(map.get_climate_at_offset(x, y));
+                rivers.push(map.get_rivers_at_offset(x, y));
+                lakes.push(map.has_lake_at_offset(x, y));
             }
             self.biome_data.push(column);
+            self.climate_data.push(climates);
+            self.river_data.push(rivers);
+            self.lake_data.push(lakes);
         }
     }
 
@@ -61,10 +89,48 @@ impl MapUI {
         self.width = w;
         self.height = h;
     }
+
+    // region rectangles are drawn centered on their offsets, so the region
+    // under a point is the one whose center is nearest to it
+    fn region_at(&self, px: f32, py: f32) -> Option<(usize, usize)> {
+        if self.biome_data.is_empty() || self.biome_data[0].is_empty() {
+            return None;
+        }
+
+        let data_width = self.biome_data.len();
+        let data_height = self.biome_data[0].len();
+        let rect_width = self.width / data_width as f32;
+        let rect_height = self.height / data_height as f32;
+
+        let x = ((px - self.x) / rect_width + 0.5).floor();
+        let y = ((py - self.y) / rect_height + 0.5).floor();
+
+        if x >= 0.0 && y >= 0.0 && (x as usize) < data_width && (y as usize) < data_height {
+            Some((x as usize, y as usize))
+        } else {
+            None
+        }
+    }
+
+    fn describe_region(&self, x: usize, y: usize) -> String {
+        let climate = self.climate_data[x][y];
+        let temperature = TEMPERATURE_MIN_C +
+            climate.temperature * (TEMPERATURE_MAX_C - TEMPERATURE_MIN_C);
+        i18n::tr_args(
+            "mapgen.region_description",
+            &[
+                x.to_string(),
+                y.to_string(),
+                map::get_biome_name(&self.biome_data[x][y]),
+                format!("{:.0}", temperature),
+                format!("{:.0}", climate.moisture * 100.0),
+            ],
+        )
+    }
 }
 
 impl ui::UIElement for MapUI {
-    fn draw(&mut self, ctx: &mut Context) {
+    fn draw(&mut self, ctx: &mut Context, style: &Style) {
         if self.biome_data.is_empty() || self.biome_data[0].is_empty() {
             return;
         }
@@ -78,11 +144,11 @@ impl ui::UIElement for MapUI {
         //first pass: draw region colors
         for x in 0..data_width {
             for y in 0..data_height {
-                let biome_color: Color = match self.biome_data[x][y] {
-                    BiomeType::Arid => *REGION_COLOR_ARID,
-                    BiomeType::Grassland => *REGION_COLOR_GRASSLAND,
-                    BiomeType::Ocean => *REGION_COLOR_OCEAN,
-                    BiomeType::Rocky => *REGION_COLOR_ROCKY,
+                // a theme can recolor a biome through the map's palette
+                let biome = &self.biome_data[x][y];
+                let biome_color: Color = match style.palette.get(biome.id()) {
+                    Some(color) => *color,
+                    None => Color::from(biomes::get(biome).color),
                 };
 
                 let rect_x = self.x + (x as f32 * rect_width);
@@ -102,32 +168,59 @@ impl ui::UIElement for MapUI {
             }
         }
 
-        //second pass: draw markings
+        //second pass: draw lakes and rivers
+        let water_color = style.get_palette_color("water");
+        graphics::set_color(ctx, water_color).unwrap();
         for x in 0..data_width {
             for y in 0..data_height {
                 let rect_x = self.x + (x as f32 * rect_width);
                 let rect_y = self.y + (y as f32 * rect_height);
 
-                if self.mouse_pos.0 > rect_x - (rect_width / 2.0) &&
-                    self.mouse_pos.0 < rect_x + (rect_width / 2.0) &&
-                    self.mouse_pos.1 > rect_y - (rect_height / 2.0) &&
-                    self.mouse_pos.1 < rect_y + (rect_height / 2.0)
-                {
-                    self.description = format!(
-                        "({}, {}): {}",
-                        x,
-                        y,
-                        map::get_biome_name(&self.biome_data[x][y])
-                    );
+                if self.lake_data[x][y] {
+                    graphics::rectangle(
+                        ctx,
+                        DrawMode::Fill,
+                        Rect {
+                            x: rect_x,
+                            y: rect_y,
+                            w: rect_width * LAKE_SCALE,
+                            h: rect_height * LAKE_SCALE,
+                        },
+                    ).unwrap();
                 }
 
+                // each half of a river runs from the middle of its region to
+                // the edge it shares with the next
+                for segment in self.river_data[x][y].iter() {
+                    let flow = segment.flow.min(RIVER_MAX_FLOW_WIDTH) as f32;
+                    graphics::set_line_width(ctx, RIVER_LINE_WIDTH + flow * RIVER_WIDTH_PER_FLOW);
+                    graphics::line(
+                        ctx,
+                        &[
+                            Point::new(rect_x, rect_y),
+                            Point::new(
+                                rect_x + segment.direction.0 as f32 * rect_width / 2.0,
+                                rect_y + segment.direction.1 as f32 * rect_height / 2.0,
+                            ),
+                        ],
+                    ).unwrap();
+                }
+            }
+        }
+
+        //third pass: draw markings
+        for x in 0..data_width {
+            for y in 0..data_height {
+                let rect_x = self.x + (x as f32 * rect_width);
+                let rect_y = self.y + (y as f32 * rect_height);
+
                 if self.selection_pos.0 > rect_x - (rect_width / 2.0) &&
                     self.selection_pos.0 < rect_x + (rect_width / 2.0) &&
                     self.selection_pos.1 > rect_y - (rect_height / 2.0) &&
                     self.selection_pos.1 < rect_y + (rect_height / 2.0)
                 {
-                    graphics::set_color(ctx, Color::from((255, 0, 0))).unwrap();
-                    graphics::set_line_width(ctx, REGION_OUTLINE_WIDTH);
+                    graphics::set_color(ctx, style.color_border).unwrap();
+                    graphics::set_line_width(ctx, style.border_width);
                     graphics::rectangle(
                         ctx,
                         DrawMode::Line,
@@ -143,8 +236,21 @@ impl ui::UIElement for MapUI {
         }
     }
 
+    fn widget_type(&self) -> &'static str {
+        "map"
+    }
+
     fn hover(&mut self, mouse_x: i32, mouse_y: i32) {
         self.mouse_pos = (mouse_x as f32, mouse_y as f32);
+        if let Some((x, y)) = self.region_at(self.mouse_pos.0, self.mouse_pos.1) {
+            self.description = self.describe_region(x, y);
+        }
+    }
+
+    fn tooltip(&self, mouse_x: i32, mouse_y: i32) -> Option<String> {
+        self.region_at(mouse_x as f32, mouse_y as f32).map(|(x, y)| {
+            self.describe_region(x, y)
+        })
     }
 
     fn click(&mut self, mouse_x: i32, mouse_y: i32, info: &mut StateInfo) {
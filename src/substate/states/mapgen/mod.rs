@@ -1,6 +1,12 @@
+mod biomes;
+mod generation;
+mod hydrology;
 pub mod map;
 mod map_ui;
+mod pool;
+pub mod region_cache;
 mod regions_generator;
+mod seed;
 
 use std::time::Duration;
 
@@ -13,6 +19,9 @@ use ggez::graphics;
 use substate::states::{StateInfo, StoredValue};
 use substate::{Status, SubState};
 use utility::ui;
+use utility::i18n;
+use utility::settings;
+use self::generation::Generation;
 use self::map::Map;
 use self::map_ui::MapUI;
 
@@ -21,14 +30,23 @@ const SEED_ELEMENT_PADDING_HORIZ: f32 = 10.0;
 const MAP_VIEW_TOP_PAD: f32 = 16.0;
 const MAP_VIEW_RIGHT_PAD: f32 = 16.0;
 const MAP_LABEL_TOP_PAD: f32 = 16.0;
+const MAP_LABEL_HEIGHT: f32 = 24.0;
 const MAP_DEFAULT_WIDTH: u32 = 36;
 const MAP_DEFAULT_HEIGHT: u32 = 36;
+const MAP_REGION_SIZE: u32 = 32;
+const PROGRESS_BAR_WIDTH: f32 = 240.0;
+const PROGRESS_BAR_HEIGHT: f32 = 20.0;
+const PROGRESS_BAR_PADDING_TOP: f32 = 8.0;
+const CONFIRM_REGEN_KEY: &'static str = "confirm_regen";
+const REGEN_DIALOG_ID: &'static str = "dlg_regen";
 
 pub struct MapGenState {
     info: StateInfo,
     has_initialized_ui: bool,
     ui_context: ui::UIContext,
     map: Map,
+    // the map being made to replace the one above, while there is one
+    generation: Option<Generation>,
 }
 
 impl MapGenState {
@@ -37,7 +55,8 @@ impl MapGenState {
             info: StateInfo::new(),
             has_initialized_ui: false,
             ui_context: ui::UIContext::new(),
-            map: Map::new(MAP_DEFAULT_WIDTH, MAP_DEFAULT_HEIGHT, 32),
+            map: Map::new(MAP_DEFAULT_WIDTH, MAP_DEFAULT_HEIGHT, MAP_REGION_SIZE),
+            generation: None,
         }
     }
 
@@ -46,9 +65,9 @@ impl MapGenState {
 
         self.ui_context.add_element(
             "btn_newSeed",
-            Box::new(ui::Button::new(
-                String::from("Generate New"),
-                MapGenState::set_random_seed,
+            Box::new(ui::Button::localized(
+                "mapgen.generate",
+                MapGenState::confirm_new_seed,
             )),
         );
 
@@ -62,6 +81,10 @@ impl MapGenState {
             Box::new(ui::Label::new(String::from("..."))),
         );
 
+        let mut progress_bar = ui::ProgressBar::new(PROGRESS_BAR_WIDTH, PROGRESS_BAR_HEIGHT);
+        progress_bar.set_visible(false);
+        self.ui_context.add_element("prg_generation", Box::new(progress_bar));
+
         self.ui_context.add_element(
             "map_overview",
             Box::new(MapUI::new()),
@@ -71,15 +94,84 @@ impl MapGenState {
         MapGenState::set_random_seed(&mut self.info);
     }
 
+    // asks before throwing the current map away; see update()
+    fn confirm_new_seed(state: &mut StateInfo) {
+        state.set_value(CONFIRM_REGEN_KEY, StoredValue::Boolean { value: true });
+        state.refresh_ui();
+    }
+
     fn set_random_seed(state: &mut StateInfo) {
         state.set_value(
             "map_seed",
-            StoredValue::Unsigned { value: rand::thread_rng().gen::<usize>() },
+            StoredValue::Seed { value: rand::thread_rng().gen::<u64>() },
         );
         state.set_value("gen_map", StoredValue::Boolean { value: true });
         state.refresh_ui();
     }
 
+    // replaces (and so cancels) any generation that's already running
+    fn start_generation(&mut self, seed: u64) {
+        let mut map = Map::new(MAP_DEFAULT_WIDTH, MAP_DEFAULT_HEIGHT, MAP_REGION_SIZE);
+        map.set_seed(seed);
+        self.generation = Some(Generation::start(map));
+        self.show_generating(true);
+    }
+
+    // goes back to showing the seed of the map that's being kept
+    fn cancel_generation(&mut self) {
+        self.generation = None;
+        self.show_generating(false);
+        self.info.set_value(
+            "map_seed",
+            StoredValue::Seed { value: self.map.get_seed() },
+        );
+        self.info.refresh_ui();
+    }
+
+    // takes the map from the worker once it's done, keeping the progress bar
+    // up to date until then
+    fn update_generation(&mut self) {
+        let finished = match self.generation {
+            Some(ref mut generation) => {
+                if let Some(map) = generation.poll() {
+                    self.map = map;
+                }
+
+                let progress = generation.get_progress();
+                self.ui_context.modify_element(
+                    "prg_generation",
+                    |bar: &mut ui::ProgressBar| {
+                        bar.set_progress(progress as f32 / 100.0);
+                        bar.set_text(i18n::tr_args("mapgen.generating", &[progress.to_string()]));
+                    },
+                );
+                generation.is_done()
+            }
+            None => false,
+        };
+
+        if finished {
+            self.generation = None;
+            self.show_generating(false);
+            self.info.refresh_ui();
+        }
+    }
+
+    // the progress bar stands in for the generate button while a map is made
+    fn show_generating(&mut self, generating: bool) {
+        self.ui_context.modify_element(
+            "btn_newSeed",
+            |btn: &mut ui::Button| btn.set_enabled(!generating),
+        );
+        self.ui_context.modify_element(
+            "prg_generation",
+            |bar: &mut ui::ProgressBar| {
+                bar.set_progress(0.0);
+                bar.set_visible(generating);
+            },
+        );
+    }
+
     fn should_regenerate_map(&mut self) -> bool {
         if let Ok(&StoredValue::Boolean { value: flag }) = self.info.get_value("gen_map") {
             return flag.clone();
@@ -97,20 +189,43 @@ impl SubState for MapGenState {
 
 impl event::EventHandler for MapGenState {
     fn update(&mut self, ctx: &mut Context, dt: Duration) -> GameResult<()> {
+        self.ui_context.update(dt);
+        self.update_generation();
 
         //check to see if ui needs to be updated after having been initialized
-        if self.info.is_ui_dirty() && self.has_initialized_ui {
+        if (self.info.is_ui_dirty() | self.ui_context.is_dirty()) && self.has_initialized_ui {
             let screen = graphics::get_screen_coordinates(ctx);
 
-            let mut map_seed = 0usize;
+            if let Some(StoredValue::Boolean { value: true }) =
+                self.info.take_value(CONFIRM_REGEN_KEY)
+            {
+                if settings::read(|s| s.gameplay.confirm_actions) {
+                    self.ui_context.show_dialog(ui::Dialog::confirm(
+                        REGEN_DIALOG_ID,
+                        "dialog.regenerate_title",
+                        "dialog.regenerate_message",
+                    ));
+                } else {
+                    MapGenState::set_random_seed(&mut self.info);
+                }
+            }
 
-            if let Ok(&StoredValue::Unsigned { value: seed }) = self.info.get_value("map_seed") {
+            if let Some(StoredValue::Textual { value: choice }) =
+                self.info.take_value(REGEN_DIALOG_ID)
+            {
+                if choice == ui::CHOICE_YES {
+                    MapGenState::set_random_seed(&mut self.info);
+                }
+            }
+
+            let mut map_seed = 0u64;
+
+            if let Ok(&StoredValue::Seed { value: seed }) = self.info.get_value("map_seed") {
                 map_seed = seed.clone();
             }
 
             if self.should_regenerate_map() {
-                self.map.set_seed(map_seed);
-                self.map.generate_regions(|_| {}); //todo: setup callback
+                self.start_generation(map_seed);
                 self.info.set_value(
                     "gen_map",
                     StoredValue::Boolean { value: false },
@@ -121,7 +236,7 @@ impl event::EventHandler for MapGenState {
             self.ui_context.modify_element(
                 "lbl_mapSeed",
                 |lbl: &mut ui::Label| {
-                    lbl.set_text(format!("Seed: {}", map_seed), ctx);
+                    lbl.set_text(i18n::tr_args("mapgen.seed", &[map_seed.to_string()]), ctx);
                     seed_label_width = lbl.get_width();
                     let height = lbl.get_height();
                     lbl.set_position(
@@ -131,28 +246,45 @@ impl event::EventHandler for MapGenState {
                 },
             );
 
+            let mut button_height: f32 = 0.0;
             self.ui_context.modify_element(
                 "btn_newSeed",
                 |btn: &mut ui::Button| {
+                    btn.set_tooltip(i18n::tr("mapgen.generate_tooltip"));
                     let width = btn.get_width();
-                    let height = btn.get_height();
+                    button_height = btn.get_height();
                     btn.set_position(
                         2.0 * SEED_ELEMENT_PADDING_HORIZ + seed_label_width + width / 2.0,
-                        SEED_ELEMENT_PADDING_TOP + height / 2.0,
+                        SEED_ELEMENT_PADDING_TOP + button_height / 2.0,
+                    );
+                },
+            );
+
+            self.ui_context.modify_element(
+                "prg_generation",
+                |bar: &mut ui::ProgressBar| {
+                    bar.set_position(
+                        SEED_ELEMENT_PADDING_HORIZ + bar.get_width() / 2.0,
+                        SEED_ELEMENT_PADDING_TOP + button_height + PROGRESS_BAR_PADDING_TOP +
+                            bar.get_height() / 2.0,
                     );
                 },
             );
 
+            // the map takes up a third of the width, unless that wouldn't
+            // leave room underneath it for the region description
+            let map_size = (screen.w / 3.0).min(
+                (screen.h * -1.0) - MAP_VIEW_TOP_PAD - MAP_LABEL_TOP_PAD - MAP_LABEL_HEIGHT,
+            );
+            let map_x = screen.w - map_size - MAP_VIEW_RIGHT_PAD;
+
             let map = &self.map;
             self.ui_context.modify_element(
                 "map_overview",
                 |map_view: &mut MapUI| {
                     map_view.update(map);
-                    map_view.set_position(
-                        (2.0 * (screen.w / 3.0)) - MAP_VIEW_RIGHT_PAD,
-                        MAP_VIEW_TOP_PAD,
-                    );
-                    map_view.set_size(screen.w / 3.0, screen.w / 3.0);
+                    map_view.set_position(map_x, MAP_VIEW_TOP_PAD);
+                    map_view.set_size(map_size, map_size);
                 },
             );
 
@@ -168,8 +300,8 @@ impl event::EventHandler for MapGenState {
                 |lbl: &mut ui::Label| {
                     lbl.set_text(map_desc.clone(), ctx);
                     lbl.set_position(
-                        (2.5 * (screen.w / 3.0)) - MAP_VIEW_RIGHT_PAD,
-                        MAP_VIEW_TOP_PAD + MAP_LABEL_TOP_PAD + screen.w / 3.0,
+                        map_x + map_size / 2.0,
+                        MAP_VIEW_TOP_PAD + MAP_LABEL_TOP_PAD + map_size,
                     );
                 },
             );
@@ -197,14 +329,45 @@ impl event::EventHandler for MapGenState {
         xrel: i32,
         yrel: i32,
     ) {
-        if !state.left() {
-            self.ui_context.mouse_moved(x, y);
-        }
+        let input = ui::Input::MouseMotion(state, x, y, xrel, yrel);
+        self.ui_context.handle_input(input, &mut self.info);
     }
 
     fn mouse_button_down_event(&mut self, button: event::MouseButton, x: i32, y: i32) {
-        if button == event::MouseButton::Left {
-            self.ui_context.click(x, y, &mut self.info);
+        self.ui_context.handle_input(ui::Input::MouseDown(button, x, y), &mut self.info);
+    }
+
+    fn mouse_button_up_event(&mut self, button: event::MouseButton, x: i32, y: i32) {
+        self.ui_context.handle_input(ui::Input::MouseUp(button, x, y), &mut self.info);
+    }
+
+    fn mouse_wheel_event(&mut self, x: i32, y: i32) {
+        self.ui_context.handle_input(ui::Input::Wheel(y), &mut self.info);
+    }
+
+    fn key_down_event(&mut self, keycode: event::Keycode, keymod: event::Mod, repeat: bool) {
+        if !self.ui_context.has_dialog() && !repeat {
+            let keys = settings::get().keys;
+            if keys.matches("regenerate_map", keycode) {
+                if self.generation.is_none() {
+                    MapGenState::confirm_new_seed(&mut self.info);
+                }
+                return;
+            } else if keys.matches("back", keycode) {
+                // the first press stops a map being made, keeping the last one
+                if self.generation.is_some() {
+                    self.cancel_generation();
+                } else {
+                    self.info.transition("menu");
+                }
+                return;
+            }
         }
+
+        self.ui_context.handle_input(ui::Input::KeyDown(keycode), &mut self.info);
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: u32, height: u32) {
+        self.info.refresh_ui();
     }
 }
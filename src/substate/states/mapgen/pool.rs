@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::channel;
+use std::thread;
+
+// one worker per core, or just the one if there's no telling
+pub fn worker_count() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+// runs `job` for every index up to `count`, spread over at most `workers`
+// threads that each take the next index as they become free, and hands the
+// results to `done` on the calling thread as they come in (not in order);
+// if `done` returns false the rest are skipped and this returns false
+pub fn run<T, J, D>(count: usize, workers: usize, job: J, mut done: D) -> bool
+where
+    T: Send,
+    J: Fn(usize) -> T + Sync,
+    D: FnMut(usize, T) -> bool,
+{
+    let next = AtomicUsize::new(0);
+    let cancelled = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        let (sender, receiver) = channel::<(usize, T)>();
+        for _ in 0..workers.max(1).min(count) {
+            let sender = sender.clone();
+            let (job, next, cancelled) = (&job, &next, &cancelled);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= count || cancelled.load(Ordering::Relaxed) {
+                    break;
+                }
+                if sender.send((index, job(index))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        for (index, result) in receiver {
+            if !done(index, result) {
+                cancelled.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+    });
+
+    !cancelled.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::run;
+
+    #[test]
+    fn every_job_is_run_once() {
+        let mut results = vec![0; 50];
+        assert!(run(50, 4, |i| i * 2, |i, r| {
+            results[i] += r + 1;
+            true
+        }));
+        assert!(results.iter().enumerate().all(|(i, r)| *r == i * 2 + 1));
+
+        let mut seen = 0;
+        assert!(!run(50, 4, |i| i, |_, _| {
+            seen += 1;
+            seen < 10
+        }));
+        assert_eq!(seen, 10);
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread;
+
+use super::map::{Map, Region};
+use super::pool;
+use super::regions_generator::RegionsGenerator;
+
+type Offset = (u32, u32);
+
+struct Request {
+    offset: Offset,
+    ticket: u64,
+    region: Region,
+    dir: String,
+    seed: u64,
+    map_height: u32,
+}
+
+// keeps the tiles of the most recently used regions of a map in memory, up to
+// a budget, saving the tiles of any that have changed when they're evicted;
+// regions should be loaded through the cache rather than the map for it to
+// know about them
+pub struct RegionCache {
+    capacity: usize,
+    // loaded regions, least recently used first
+    recent: VecDeque<Offset>,
+    // regions the loader has been asked for but hasn't sent back yet, each
+    // with the ticket of the request; a result whose ticket isn't here any
+    // more is stale, since the region could have been loaded, changed and
+    // saved since it was asked for
+    pending: HashMap<Offset, u64>,
+    next_ticket: u64,
+    requests: Sender<Request>,
+    loaded: Receiver<(Offset, u64, Result<Region, String>)>,
+}
+
+impl RegionCache {
+    pub fn new(capacity: usize) -> RegionCache {
+        let (requests, request_receiver) = channel::<Request>();
+        let (loaded_sender, loaded) = channel::<(Offset, u64, Result<Region, String>)>();
+
+        // the loader stops once the cache, and with it the sending end of
+        // the requests, is gone
+        thread::spawn(move || {
+            while let Ok(first) = request_receiver.recv() {
+                // everything asked for since the last batch is loaded
+                // together, spread over the worker pool
+                let mut batch = vec![first];
+                batch.extend(request_receiver.try_iter());
+
+                let mut generators = Vec::<((u64, u32), RegionsGenerator)>::new();
+                for request in batch.iter() {
+                    let key = (request.seed, request.map_height);
+                    if !generators.iter().any(|g| g.0 == key) {
+                        generators.push((key, RegionsGenerator::new(key.0, key.1)));
+                    }
+                }
+
+                let finished = pool::run(
+                    batch.len(),
+                    pool::worker_count(),
+                    |i| {
+                        let request = &batch[i];
+                        let key = (request.seed, request.map_height);
+                        let region_gen = &generators.iter().find(|g| g.0 == key).unwrap().1;
+                        let (x, y) = request.offset;
+                        let mut region = request.region.detached();
+                        region
+                            .load_or_generate_tiles(request.dir.clone(), region_gen, x, y)
+                            .map(|_| region)
+                    },
+                    |i, loaded| {
+                        let request = &batch[i];
+                        loaded_sender.send((request.offset, request.ticket, loaded)).is_ok()
+                    },
+                );
+                if !finished {
+                    break;
+                }
+            }
+        });
+
+        RegionCache {
+            capacity: capacity.max(1),
+            recent: VecDeque::<Offset>::new(),
+            pending: HashMap::<Offset, u64>::new(),
+            next_ticket: 0,
+            requests: requests,
+            loaded: loaded,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.recent.len()
+    }
+
+    // the region's tiles, loaded there and then if they aren't already; a
+    // background load of the region that's still on its way is thrown away
+    pub fn get<'a>(&mut self, map: &'a mut Map, x: u32, y: u32) -> Result<&'a mut Region, String> {
+        map.load_region(x, y)?;
+        self.pending.remove(&(x, y));
+        self.touch((x, y));
+        self.evict(map)?;
+        map.load_region(x, y)
+    }
+
+    // starts loading the regions within `radius` of the given one in the
+    // background, so they're ready by the time they're needed; the square
+    // around it should fit in the cache, or the far side of it will push out
+    // the near
+    pub fn focus(&mut self, map: &Map, x: u32, y: u32, radius: u32) {
+        let min_x = x.saturating_sub(radius);
+        let min_y = y.saturating_sub(radius);
+        let max_x = (x + radius).min(map.get_width() - 1);
+        let max_y = (y + radius).min(map.get_height() - 1);
+
+        for nx in min_x..max_x + 1 {
+            for ny in min_y..max_y + 1 {
+                let offset = (nx, ny);
+                if self.pending.contains_key(&offset) {
+                    continue;
+                }
+                if map.is_region_loaded(nx, ny) {
+                    self.touch(offset);
+                    continue;
+                }
+
+                self.next_ticket += 1;
+                let request = Request {
+                    offset: offset,
+                    ticket: self.next_ticket,
+                    region: map.detach_region(nx, ny),
+                    dir: map.get_directory(),
+                    seed: map.get_seed(),
+                    map_height: map.get_height(),
+                };
+                if self.requests.send(request).is_ok() {
+                    self.pending.insert(offset, self.next_ticket);
+                }
+            }
+        }
+    }
+
+    // puts whatever the loader has finished into the map, evicting the least
+    // recently used regions to make room; a region that couldn't be loaded
+    // is left unloaded, and the first such error is returned
+    pub fn update(&mut self, map: &mut Map) -> Result<(), String> {
+        let mut failure: Option<String> = None;
+        while let Ok((offset, ticket, loaded)) = self.loaded.try_recv() {
+            if self.pending.get(&offset) != Some(&ticket) {
+                continue;
+            }
+            self.pending.remove(&offset);
+
+            match loaded {
+                Ok(region) => {
+                    // the region may have been loaded directly while this was
+                    // on its way
+                    if !map.is_region_loaded(offset.0, offset.1) {
+                        map.attach_tiles(offset.0, offset.1, region);
+                    }
+                    self.touch(offset);
+                }
+                Err(why) => {
+                    failure = failure.or(Some(why));
+                }
+            }
+        }
+        self.evict(map)?;
+
+        match failure {
+            Some(why) => Err(why),
+            None => Ok(()),
+        }
+    }
+
+    // true while there are regions still being loaded in the background
+    pub fn is_loading(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    // unloads everything, saving whatever's changed; regions that can't be
+    // saved stay loaded
+    pub fn clear(&mut self, map: &mut Map) -> Result<(), String> {
+        while let Some((x, y)) = self.recent.pop_front() {
+            if let Err(why) = map.unload_region(x, y) {
+                self.recent.push_front((x, y));
+                return Err(why);
+            }
+        }
+        Ok(())
+    }
+
+    fn touch(&mut self, offset: Offset) {
+        if let Some(index) = self.recent.iter().position(|o| *o == offset) {
+            self.recent.remove(index);
+        }
+        self.recent.push_back(offset);
+    }
+
+    // a region whose tiles can't be saved stays in the cache, over budget,
+    // rather than losing its changes
+    fn evict(&mut self, map: &mut Map) -> Result<(), String> {
+        while self.recent.len() > self.capacity {
+            if let Some((x, y)) = self.recent.pop_front() {
+                if let Err(why) = map.unload_region(x, y) {
+                    self.recent.push_front((x, y));
+                    return Err(why);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::super::map::{Map, TileType};
+    use super::RegionCache;
+
+    fn test_map(name: &str) -> Map {
+        let mut map = Map::new(5, 5, 8);
+        map.set_seed(3);
+        map.set_root(env::temp_dir().join(name).to_str().unwrap());
+        map.generate_regions(|_| true);
+        map
+    }
+
+    fn saved_regions(map: &Map) -> usize {
+        fs::read_dir(map.get_directory()).map_or(0, |entries| entries.count())
+    }
+
+    #[test]
+    fn least_recently_used_regions_are_evicted_and_saved() {
+        let mut map = test_map("roguelike_region_cache_lru");
+        let mut cache = RegionCache::new(2);
+
+        cache.get(&mut map, 0, 0).unwrap();
+        cache.get(&mut map, 1, 0).unwrap();
+        cache.get(&mut map, 0, 0).unwrap();
+        assert_eq!(saved_regions(&map), 0);
+
+        //(1, 0) was used longest ago, so it makes way
+        cache.get(&mut map, 2, 0).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(map.is_region_loaded(0, 0));
+        assert!(!map.is_region_loaded(1, 0));
+        assert_eq!(saved_regions(&map), 1);
+
+        //saving the map leaves loaded regions loaded, and they come back from
+        //their files unchanged
+        map.save().unwrap();
+        assert!(map.is_region_loaded(2, 0));
+        let tiles = format!("{:?}", cache.get(&mut map, 2, 0).unwrap());
+        cache.get(&mut map, 1, 0).unwrap();
+        cache.get(&mut map, 0, 0).unwrap();
+        assert_eq!(format!("{:?}", cache.get(&mut map, 2, 0).unwrap()), tiles);
+
+        fs::remove_dir_all(map.get_directory()).unwrap();
+    }
+
+    #[test]
+    fn regions_around_the_focus_are_loaded_in_the_background() {
+        let mut map = test_map("roguelike_region_cache_focus");
+        let mut cache = RegionCache::new(9);
+
+        cache.focus(&map, 0, 0, 1);
+        for _ in 0..500 {
+            cache.update(&mut map).unwrap();
+            if !cache.is_loading() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(cache.len(), 4);
+        for &(x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)].iter() {
+            assert!(map.is_region_loaded(x, y));
+        }
+        assert!(!map.is_region_loaded(2, 2));
+
+        cache.clear(&mut map).unwrap();
+        assert!(!map.is_region_loaded(0, 0));
+        assert_eq!(saved_regions(&map), 4);
+
+        fs::remove_dir_all(map.get_directory()).unwrap();
+    }
+
+    #[test]
+    fn late_background_loads_never_replace_saved_changes() {
+        let mut map = test_map("roguelike_region_cache_stale");
+        let mut cache = RegionCache::new(1);
+
+        //asked for in the background, then needed straight away and changed
+        cache.focus(&map, 0, 0, 0);
+        cache.get(&mut map, 0, 0).unwrap().set_tile(1, 1, 0, TileType::Air, false);
+
+        //saved as it's evicted, and then the background load turns up
+        cache.get(&mut map, 1, 0).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        cache.update(&mut map).unwrap();
+        cache.get(&mut map, 2, 0).unwrap();
+
+        let region = cache.get(&mut map, 0, 0).unwrap();
+        assert_eq!(region.get_tile_type(1, 1, 0), TileType::Air);
+
+        fs::remove_dir_all(map.get_directory()).unwrap();
+    }
+}
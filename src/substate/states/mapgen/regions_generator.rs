@@ -3,37 +3,95 @@ use std::f32::consts::PI;
 
 use noise::{Seedable, Fbm, NoiseModule};
 
+use super::biomes;
+use super::biomes::Terrain;
 use super::map::BiomeType;
+use super::seed;
 
-const SEED_MOD_ARID: usize = 2_196_648_173;
-const SEED_MOD_GRASSLAND: usize = 4_288_859_287;
-const SEED_MOD_OCEAN: usize = 1_880_045_689;
-const SEED_MOD_ROCKY: usize = 688_937_993;
+const SEED_SALT_TERRAIN: u64 = 0x7465_7272_6169_6E00;
+const SEED_SALT_TEMPERATURE: u64 = 0x7465_6D70_6572_6174;
+const SEED_SALT_MOISTURE: u64 = 0x6D6F_6973_7475_7265;
 const NOISE_SCALE: f32 = 0.1;
 const SMOOTHNESS: f32 = 8.0;
 const SEA_LEVEL: f32 = 0.1;
 const VALLEY_MAX_STEEPNESS: f32 = 0.07;
-const GRASSLAND_COVERAGE: f32 = 0.2;
+// stretches the climate noise out to cover more of the 0 to 1 range
+const CLIMATE_CONTRAST: f32 = 1.5;
+// how much colder it gets with height above the sea
+const LAPSE_RATE: f32 = 0.5;
+// how much of the temperature comes from distance to the poles rather than noise
+const LATITUDE_WEIGHT: f32 = 0.6;
+// wind blows from the west; high ground up to this many regions upwind dries
+// out the land behind it
+const RAIN_SHADOW_REACH: u32 = 4;
+const RAIN_SHADOW_STRENGTH: f32 = 1.5;
+// small bumps in the ground on top of the terrain noise, per tile
+const DETAIL_SCALE: f32 = 0.08;
+const DETAIL_AMOUNT: f32 = 0.15;
+// how many tiles either side of a region border the biomes blend over
+const BLEND_TILES: f32 = 8.0;
+
+// what the ground at one tile looks like once the biomes around it are
+// blended together
+pub struct TileTerrain {
+    pub height: f32,
+    // the biome the tile's surface is made of; near borders this is picked
+    // from the blended biomes, so transitions are mixed rather than straight
+    pub biome: BiomeType,
+}
+
+// the biomes of a region and the regions around it, which are all its tiles
+// ever blend between; working them out once per region saves doing it again
+// for every tile
+pub struct BiomeNeighborhood {
+    min_x: u32,
+    min_y: u32,
+    biomes: Vec<BiomeType>,
+}
+
+impl BiomeNeighborhood {
+    fn get(&self, map_x: u32, map_y: u32) -> BiomeType {
+        let index = (map_y - self.min_y) * 3 + (map_x - self.min_x);
+        self.biomes[index as usize].clone()
+    }
+}
+
+// the weather a region gets, each part from 0 to 1
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug, Default)]
+pub struct Climate {
+    pub temperature: f32,
+    pub moisture: f32,
+}
 
 pub struct RegionsGenerator {
-    seed: usize,
+    seed: u64,
+    map_height: u32,
     biome_noise: HashMap<BiomeType, Fbm<f32>>,
     terrain_noise: Fbm<f32>,
+    temperature_noise: Fbm<f32>,
+    moisture_noise: Fbm<f32>,
 }
 
 impl RegionsGenerator {
-    pub fn new(seed: usize) -> RegionsGenerator {
+    // the map's height sets where the poles are, at its top and bottom edges
+    pub fn new(seed: u64, map_height: u32) -> RegionsGenerator {
         let mut noise_factories = HashMap::<BiomeType, Fbm<f32>>::new();
-
-        RegionsGenerator::register_biome_type(&seed, &mut noise_factories, BiomeType::Arid);
-        RegionsGenerator::register_biome_type(&seed, &mut noise_factories, BiomeType::Grassland);
-        RegionsGenerator::register_biome_type(&seed, &mut noise_factories, BiomeType::Ocean);
-        RegionsGenerator::register_biome_type(&seed, &mut noise_factories, BiomeType::Rocky);
+        for def in biomes::registry().biomes.iter() {
+            noise_factories.insert(
+                def.get_biome_type(),
+                Fbm::<f32>::new().set_seed(seed::noise_seed(seed, def.seed_salt)),
+            );
+        }
 
         RegionsGenerator {
             seed: seed,
+            map_height: map_height,
             biome_noise: noise_factories,
-            terrain_noise: Fbm::<f32>::new().set_seed(seed),
+            terrain_noise: Fbm::<f32>::new().set_seed(seed::noise_seed(seed, SEED_SALT_TERRAIN)),
+            temperature_noise: Fbm::<f32>::new().set_seed(
+                seed::noise_seed(seed, SEED_SALT_TEMPERATURE),
+            ),
+            moisture_noise: Fbm::<f32>::new().set_seed(seed::noise_seed(seed, SEED_SALT_MOISTURE)),
         }
     }
 
@@ -44,23 +102,137 @@ impl RegionsGenerator {
         let height: f32 = self.terrain_noise.get([x, y]);
         let steepness = (height - local_avg).abs();
 
-        if height <= SEA_LEVEL {
-            BiomeType::Ocean
+        let terrain = if height <= SEA_LEVEL {
+            Terrain::Water
+        } else if steepness > VALLEY_MAX_STEEPNESS {
+            Terrain::Steep
         } else {
-            if steepness > VALLEY_MAX_STEEPNESS {
-                BiomeType::Rocky
-            } else {
-                if let Some(factory) = self.biome_noise.get(&BiomeType::Grassland) {
-                    if factory.get([x, y]) <= GRASSLAND_COVERAGE {
-                        BiomeType::Grassland
-                    } else {
-                        BiomeType::Arid
-                    }
-                } else {
-                    BiomeType::Arid
-                }
+            Terrain::Land
+        };
+
+        let climate = self.get_climate_at_point(map_x, map_y);
+        biomes::registry().select(terrain, height, climate.temperature, climate.moisture)
+    }
+
+    // the height of the ground at a region, on the same scale as the sea level
+    pub fn get_height_at_point(&self, map_x: u32, map_y: u32) -> f32 {
+        self.terrain_noise.get([NOISE_SCALE * (map_x as f32), NOISE_SCALE * (map_y as f32)])
+    }
+
+    // warmest at the equator across the middle of the map and colder with
+    // height; wetter or drier with noise, and dry behind high ground
+    pub fn get_climate_at_point(&self, map_x: u32, map_y: u32) -> Climate {
+        let x = NOISE_SCALE * (map_x as f32);
+        let y = NOISE_SCALE * (map_y as f32);
+        let height: f32 = self.terrain_noise.get([x, y]);
+
+        let latitude = if self.map_height > 1 {
+            ((map_y as f32 / (self.map_height - 1) as f32) - 0.5).abs() * 2.0
+        } else {
+            0.0
+        };
+        let temperature = (1.0 - latitude) * LATITUDE_WEIGHT +
+            climate_value(self.temperature_noise.get([x, y])) * (1.0 - LATITUDE_WEIGHT) -
+            (height - SEA_LEVEL).max(0.0) * LAPSE_RATE;
+        let moisture = climate_value(self.moisture_noise.get([x, y])) -
+            self.get_rain_shadow(x, y, height);
+
+        Climate {
+            temperature: temperature.max(0.0).min(1.0),
+            moisture: moisture.max(0.0).min(1.0),
+        }
+    }
+
+    // how much moisture the wind has lost crossing higher ground on its way
+    // to a point, the nearer the ground the more it counts
+    fn get_rain_shadow(&self, x: f32, y: f32, height: f32) -> f32 {
+        let ground = height.max(SEA_LEVEL);
+        let mut shadow: f32 = 0.0;
+        for step in 1..(RAIN_SHADOW_REACH + 1) {
+            let upwind: f32 = self.terrain_noise.get([x - NOISE_SCALE * step as f32, y]);
+            if upwind > ground {
+                shadow = shadow.max((upwind - ground) * RAIN_SHADOW_STRENGTH / step as f32);
+            }
+        }
+        shadow
+    }
+
+    pub fn get_neighborhood(&self, region_x: u32, region_y: u32) -> BiomeNeighborhood {
+        let min_x = region_x.saturating_sub(1);
+        let min_y = region_y.saturating_sub(1);
+        let mut biomes = Vec::<BiomeType>::new();
+        for y in min_y..min_y + 3 {
+            for x in min_x..min_x + 3 {
+                biomes.push(self.get_biome_at_point(x, y));
+            }
+        }
+
+        BiomeNeighborhood {
+            min_x: min_x,
+            min_y: min_y,
+            biomes: biomes,
+        }
+    }
+
+    // how much each biome contributes at a tile, summing to one; a region's
+    // biome is at full strength around its middle and fades into its
+    // neighbors' over BLEND_TILES either side of the border. Tiles are counted
+    // across the whole map rather than within a region, so the weights
+    // carry on smoothly from one region into the next
+    pub fn get_biome_weights_at_tile(
+        &self,
+        tile_x: u32,
+        tile_y: u32,
+        region_size: u32,
+    ) -> Vec<(BiomeType, f32)> {
+        blend_biomes(tile_x, tile_y, region_size, |x, y| self.get_biome_at_point(x, y))
+    }
+
+    // the height of the ground at a tile, on the same scale as the sea level;
+    // it follows the same noise that picked the biomes, raised or lowered and
+    // roughened by the blended biomes. The neighborhood has to be the one of
+    // the region the tile is in
+    pub fn get_terrain_at_tile(
+        &self,
+        tile_x: u32,
+        tile_y: u32,
+        region_size: u32,
+        neighborhood: &BiomeNeighborhood,
+    ) -> TileTerrain {
+        let scale = NOISE_SCALE / region_size as f32;
+        let x = scale * (tile_x as f32);
+        let y = scale * (tile_y as f32);
+        let detail_point = [DETAIL_SCALE * (tile_x as f32), DETAIL_SCALE * (tile_y as f32)];
+        let mut height: f32 = self.terrain_noise.get([x, y]);
+
+        // each biome roughens the ground with its own noise
+        let weights = blend_biomes(tile_x, tile_y, region_size, |x, y| neighborhood.get(x, y));
+        for &(ref biome, weight) in weights.iter() {
+            let def = biomes::get(biome);
+            let detail = self.biome_noise.get(biome).map_or(0.0, |n| n.get(detail_point));
+            height += weight * (def.elevation + detail * DETAIL_AMOUNT * def.roughness);
+        }
+
+        // picks the surface biome by weight, with a different roll per tile
+        let roll = seed::unit_at(self.seed, tile_x, tile_y);
+        let mut total = 0.0;
+        let mut surface = weights[0].0.clone();
+        for &(ref biome, weight) in weights.iter() {
+            surface = biome.clone();
+            total += weight;
+            if roll < total {
+                break;
             }
         }
+
+        TileTerrain {
+            height: height,
+            biome: surface,
+        }
+    }
+
+    pub fn get_sea_level(&self) -> f32 {
+        SEA_LEVEL
     }
 
     fn get_noise_neighbor_average(&self, noise: &Fbm<f32>, x: f32, y: f32) -> f32 {
@@ -76,20 +248,190 @@ impl RegionsGenerator {
         }
         total / ((2.0 * PI) / smoothing)
     }
+}
+
+// the weights of the biomes at the middles of the four regions nearest a
+// tile, as described for get_biome_weights_at_tile
+fn blend_biomes<F>(
+    tile_x: u32,
+    tile_y: u32,
+    region_size: u32,
+    biome_at: F,
+) -> Vec<(BiomeType, f32)>
+where
+    F: Fn(u32, u32) -> BiomeType,
+{
+    let size = region_size as f32;
+    let (x0, tx) = blend_axis(tile_x, size);
+    let (y0, ty) = blend_axis(tile_y, size);
+
+    let corners = [
+        (x0, y0, (1.0 - tx) * (1.0 - ty)),
+        (x0 + 1, y0, tx * (1.0 - ty)),
+        (x0, y0 + 1, (1.0 - tx) * ty),
+        (x0 + 1, y0 + 1, tx * ty),
+    ];
+
+    let mut weights = Vec::<(BiomeType, f32)>::new();
+    for &(x, y, weight) in corners.iter() {
+        if weight <= 0.0 {
+            continue;
+        }
+
+        let biome = biome_at(x, y);
+        match weights.iter().position(|w| w.0 == biome) {
+            Some(i) => weights[i].1 += weight,
+            None => weights.push((biome, weight)),
+        }
+    }
+    weights
+}
+
+// where a tile sits between the middles of the two nearest regions along one
+// axis: the first of those regions, and how far towards the second (0 to 1),
+// sharpened so that only tiles near the border between them are blended
+fn blend_axis(tile: u32, region_size: f32) -> (u32, f32) {
+    let position = (tile as f32 + 0.5) / region_size - 0.5;
+    if position < 0.0 {
+        return (0, 0.0);
+    }
+
+    let first = position.floor();
+    let t = (position - first - 0.5) * region_size / (2.0 * BLEND_TILES) + 0.5;
+    let t = t.max(0.0).min(1.0);
+    (first as u32, t * t * (3.0 - 2.0 * t))
+}
+
+// maps climate noise onto 0 to 1
+fn climate_value(noise: f32) -> f32 {
+    ((noise * CLIMATE_CONTRAST + 1.0) / 2.0).max(0.0).min(1.0)
+}
+
+#[cfg(test)]
+mod test {
+    use noise::NoiseModule;
+
+    use super::{blend_biomes, climate_value, RegionsGenerator, NOISE_SCALE, SEA_LEVEL};
+
+    const SIZE: u32 = 32;
+
+    // where a region is in the noise
+    fn point(map_x: u32, map_y: u32) -> [f32; 2] {
+        [NOISE_SCALE * (map_x as f32), NOISE_SCALE * (map_y as f32)]
+    }
 
-    fn register_biome_type(seed: &usize, map: &mut HashMap<BiomeType, Fbm<f32>>, biome: BiomeType) {
-        map.insert(
-            biome.clone(),
-            Fbm::<f32>::new().set_seed(seed % RegionsGenerator::get_biome_seed(biome)),
+    // the first two letters of each region's biome, row by row
+    fn biome_grid(region_gen: &RegionsGenerator, width: u32, height: u32) -> Vec<String> {
+        (0..height)
+            .map(|y| {
+                let row: Vec<String> = (0..width)
+                    .map(|x| region_gen.get_biome_at_point(x, y).id()[..2].to_string())
+                    .collect();
+                row.join(" ")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_seed_always_makes_the_same_world() {
+        //if this changes, bump seed::GENERATOR_VERSION along with the grid,
+        //including for changes to resources/biomes.json
+        let grid = biome_grid(&RegionsGenerator::new(1, 8), 12, 8);
+        assert_eq!(
+            grid,
+            vec![
+                "oc tu mo tu gr oc oc oc oc oc oc tu",
+                "oc ta tu gr gr oc oc oc oc oc oc ta",
+                "oc ta ta gr oc oc oc oc oc oc oc oc",
+                "gr fo fo gr oc oc oc oc oc oc oc oc",
+                "gr ta mo gr ro oc oc oc oc oc oc oc",
+                "ta ta mo ro ta oc oc oc oc oc oc oc",
+                "ta tu ro mo tu oc oc oc oc oc oc oc",
+                "tu tu tu mo mo tu oc oc oc oc oc oc",
+            ]
         );
+
+        //the high half of the seed counts too, whatever the target's usize
+        let high = biome_grid(&RegionsGenerator::new(1 | (1 << 40), 8), 12, 8);
+        assert!(high != grid);
+    }
+
+    #[test]
+    fn biome_weights_sum_to_one() {
+        let region_gen = RegionsGenerator::new(99, 36);
+        for &(x, y) in [(0, 0), (16, 16), (31, 40), (100, 63), (250, 250)].iter() {
+            let weights = region_gen.get_biome_weights_at_tile(x, y, SIZE);
+            let total: f32 = weights.iter().map(|w| w.1).sum();
+            assert!((total - 1.0).abs() < 0.0001, "{:?}", weights);
+        }
+    }
+
+    #[test]
+    fn a_regions_neighborhood_has_every_biome_its_tiles_blend() {
+        let region_gen = RegionsGenerator::new(99, 36);
+        let size = 8;
+        for &(rx, ry) in [(0, 0), (1, 2), (3, 0), (5, 4)].iter() {
+            let neighborhood = region_gen.get_neighborhood(rx, ry);
+            for x in rx * size..(rx + 1) * size {
+                for y in ry * size..(ry + 1) * size {
+                    assert_eq!(
+                        blend_biomes(x, y, size, |bx, by| neighborhood.get(bx, by)),
+                        region_gen.get_biome_weights_at_tile(x, y, size)
+                    );
+                }
+            }
+        }
     }
 
-    fn get_biome_seed(biome: BiomeType) -> usize {
-        match biome {
-            BiomeType::Arid => SEED_MOD_ARID,
-            BiomeType::Grassland => SEED_MOD_GRASSLAND,
-            BiomeType::Ocean => SEED_MOD_OCEAN,
-            BiomeType::Rocky => SEED_MOD_ROCKY,
+    #[test]
+    fn regions_are_their_own_biome_away_from_borders() {
+        let region_gen = RegionsGenerator::new(99, 36);
+        for rx in 0..6 {
+            let weights = region_gen.get_biome_weights_at_tile(rx * SIZE + SIZE / 2, SIZE / 2, SIZE);
+            assert_eq!(weights, vec![(region_gen.get_biome_at_point(rx, 0), 1.0)]);
         }
     }
+
+    #[test]
+    fn it_gets_colder_towards_the_poles() {
+        let region_gen = RegionsGenerator::new(7, 101);
+
+        let average = |y: u32| {
+            let total: f32 = (0..100)
+                .map(|x| region_gen.get_climate_at_point(x, y).temperature)
+                .sum();
+            total / 100.0
+        };
+        assert!(average(50) > average(0) + 0.2);
+        assert!(average(50) > average(100) + 0.2);
+
+        for x in 0..20 {
+            let climate = region_gen.get_climate_at_point(x, x);
+            assert!(climate.temperature >= 0.0 && climate.temperature <= 1.0);
+            assert!(climate.moisture >= 0.0 && climate.moisture <= 1.0);
+        }
+    }
+
+    #[test]
+    fn high_ground_dries_out_the_land_downwind() {
+        let region_gen = RegionsGenerator::new(7, 101);
+
+        //a point on land with higher ground just to the west of it
+        let (x, y) = (1..100)
+            .flat_map(|y| (1..100).map(move |x| (x, y)))
+            .find(|&(x, y)| {
+                let height = region_gen.get_height_at_point(x, y);
+                let raw = climate_value(region_gen.moisture_noise.get(point(x, y)));
+                height > SEA_LEVEL && region_gen.get_height_at_point(x - 1, y) > height &&
+                    raw > 0.0
+            })
+            .unwrap();
+
+        let p = point(x, y);
+        let height = region_gen.get_height_at_point(x, y);
+        assert!(region_gen.get_rain_shadow(p[0], p[1], height) > 0.0);
+
+        let raw = climate_value(region_gen.moisture_noise.get(p));
+        assert!(region_gen.get_climate_at_point(x, y).moisture < raw);
+    }
 }
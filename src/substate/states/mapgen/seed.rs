@@ -0,0 +1,69 @@
+// world generation only ever works from a u64 seed and the integer mixing
+// below, so a seed gives the same world on every platform; bump the version
+// whenever a change to the code would make an existing seed give a different
+// world. Changes to the biomes are caught by their hash instead
+pub const GENERATOR_VERSION: u32 = 1;
+
+// noise seeds are kept to 31 bits: the noise library adds the octave to the
+// seed as a usize, then truncates it to a u32 for its permutation tables
+const NOISE_SEED_BITS: u32 = 31;
+
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// the splitmix64 finalizer; every bit of the input affects every bit of the
+// output
+pub fn mix(value: u64) -> u64 {
+    let mut z = value;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// a seed for one part of generation, so that parts sharing the map seed don't
+// share their noise
+pub fn derive(seed: u64, salt: u64) -> u64 {
+    mix(seed.wrapping_add(GOLDEN_GAMMA).wrapping_add(mix(salt)))
+}
+
+// a derived seed that fits a noise module on both 32 and 64 bit targets
+pub fn noise_seed(seed: u64, salt: u64) -> usize {
+    (derive(seed, salt) >> (64 - NOISE_SEED_BITS)) as usize
+}
+
+// a hash of some data that's the same on every platform, eight bytes at a time
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.chunks(8).fold(mix(bytes.len() as u64), |h, chunk| {
+        let word = chunk.iter().rev().fold(0u64, |w, b| (w << 8) | *b as u64);
+        mix(h.wrapping_add(GOLDEN_GAMMA) ^ word)
+    })
+}
+
+// a number from 0 to 1 that's the same every time for a point and seed
+pub fn unit_at(seed: u64, x: u32, y: u32) -> f32 {
+    let mut h = seed ^ GOLDEN_GAMMA;
+    h ^= (x as u64).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h ^= (y as u64).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (mix(h) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::{derive, hash, noise_seed, unit_at};
+
+    #[test]
+    fn derived_seeds_never_change() {
+        //if these change, so does every world; see GENERATOR_VERSION
+        assert_eq!(derive(0, 0), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(derive(12_345, 1), 0xA43E_DC05_A614_10CF);
+        assert_eq!(noise_seed(u64::max_value(), 7), 1_212_476_698);
+        assert_eq!(unit_at(42, 3, 4), 0.41776294);
+    }
+
+    #[test]
+    fn hashes_never_change() {
+        //saved maps keep these to check they still match their biomes
+        assert_eq!(hash(b"biomes"), 0x6963_2BA8_636B_87DB);
+        assert!(hash(b"biomes!") != hash(b"biomes"));
+        assert!(hash(&[0]) != hash(&[0, 0]));
+    }
+}
@@ -4,19 +4,32 @@ use ggez::{GameResult, Context};
 use ggez::event;
 use ggez::graphics;
 
-use substate::states::StateInfo;
+use substate::states::{StateInfo, StoredValue};
 use substate::{Status, SubState};
 use utility::ui;
+use utility::settings;
+use utility::viewport;
 
+const LAYOUT_FILE: &'static str = "resources/layouts/menu.json";
+// used if the file is missing or broken
+const DEFAULT_LAYOUT: &'static str = include_str!("../../../../resources/layouts/menu.json");
 const TITLE_TOP_PAD: f32 = 48.0;
-const BTN_WIDTH: f32 = 120.0;
 const BTN_SPACING: f32 = 6.0;
 const PLAY_BTN_TOP_PAD: f32 = 200.0;
+const EMBLEM_TOP_PAD: f32 = 136.0;
+const SLIDE_SECS: f32 = 0.5;
+const SLIDE_STAGGER_SECS: f32 = 0.08;
+const RELAYOUT_SECS: f32 = 0.2;
+const QUIT_DIALOG_ID: &'static str = "dlg_quit";
+
+// the menu buttons from the layout, top to bottom
+const MENU_BUTTONS: [&'static str; 4] = ["btn_play", "btn_about", "btn_options", "btn_quit"];
 
 pub struct MenuState {
     info: StateInfo,
     has_initialized_ui: bool,
     ui_context: ui::UIContext,
+    slide_in: bool,
 }
 
 impl MenuState {
@@ -25,40 +38,16 @@ impl MenuState {
             info: StateInfo::new(),
             has_initialized_ui: false,
             ui_context: ui::UIContext::new(),
+            slide_in: true,
         }
     }
 
     fn init_ui(&mut self, ctx: &mut Context) {
         graphics::set_background_color(ctx, graphics::BLACK);
 
-        self.ui_context.add_element(
-            "lbl_title",
-            Box::new(ui::Label::new(String::from("Main Menu"))),
-        );
-
-        self.ui_context.add_element(
-            "btn_play",
-            Box::new(ui::Button::new(
-                String::from("Play"),
-                |state: &mut StateInfo| { state.transition("mapgen"); },
-            )),
-        );
-
-        self.ui_context.add_element(
-            "btn_about",
-            Box::new(ui::Button::new(
-                String::from("About"),
-                |state: &mut StateInfo| { state.transition("about"); },
-            )),
-        );
-
-        self.ui_context.add_element(
-            "btn_quit",
-            Box::new(ui::Button::new(
-                String::from("Quit"),
-                |state: &mut StateInfo| { state.quit(); },
-            )),
-        );
+        if let Err(why) = self.ui_context.load_layout(LAYOUT_FILE, DEFAULT_LAYOUT) {
+            println!("Unable to load the menu layout, using the built-in one: {}", why);
+        }
 
         self.has_initialized_ui = true;
     }
@@ -72,48 +61,89 @@ impl SubState for MenuState {
 
 impl event::EventHandler for MenuState {
     fn update(&mut self, ctx: &mut Context, dt: Duration) -> GameResult<()> {
+        self.ui_context.update(dt);
 
         //check to see if ui needs to be updated after having been initialized
-        if self.info.is_ui_dirty() && self.has_initialized_ui {
+        if (self.info.is_ui_dirty() | self.ui_context.is_dirty()) && self.has_initialized_ui {
             let screen = graphics::get_screen_coordinates(ctx);
 
+            // the menu is laid out for the virtual resolution; any extra
+            // height the window has is split above and below it
+            let top = ((screen.h * -1.0) - viewport::VIRTUAL_HEIGHT).max(0.0) / 2.0;
+
+            for action in self.info.take_actions() {
+                match action.as_str() {
+                    "play" => self.info.transition("mapgen"),
+                    "about" => self.info.transition("about"),
+                    "options" => self.info.transition("options"),
+                    "quit" => {
+                        if settings::read(|s| s.gameplay.confirm_actions) {
+                            self.ui_context.show_dialog(ui::Dialog::confirm(
+                                QUIT_DIALOG_ID,
+                                "dialog.quit_title",
+                                "dialog.quit_message",
+                            ));
+                        } else {
+                            self.info.quit();
+                        }
+                    }
+                    _ => println!("Unknown menu action \"{}\"", action),
+                }
+            }
+
+            if let Some(StoredValue::Textual { value: choice }) =
+                self.info.take_value(QUIT_DIALOG_ID)
+            {
+                if choice == ui::CHOICE_YES {
+                    self.info.quit();
+                }
+            }
+
             self.ui_context.modify_element(
                 "lbl_title",
                 |lbl: &mut ui::Label| {
                     let height = lbl.get_height();
-                    lbl.set_position(screen.w / 2.0, TITLE_TOP_PAD + height / 2.0);
+                    lbl.set_position(screen.w / 2.0, top + TITLE_TOP_PAD + height / 2.0);
                 },
             );
 
             self.ui_context.modify_element(
-                "btn_play",
-                |btn: &mut ui::Button| {
-                    let height = btn.get_height();
-                    btn.set_position(screen.w / 2.0, PLAY_BTN_TOP_PAD);
-                    btn.set_width(BTN_WIDTH);
-                },
+                "img_emblem",
+                |img: &mut ui::Image| { img.set_position(screen.w / 2.0, top + EMBLEM_TOP_PAD); },
             );
 
-            self.ui_context.modify_element(
-                "btn_about",
-                |btn: &mut ui::Button| {
-                    let height = btn.get_height();
-                    btn.set_position(screen.w / 2.0, height + BTN_SPACING + PLAY_BTN_TOP_PAD);
-                    btn.set_width(BTN_WIDTH);
-                },
-            );
-
-            self.ui_context.modify_element(
-                "btn_quit",
-                |btn: &mut ui::Button| {
-                    let height = btn.get_height();
-                    btn.set_position(
-                        screen.w / 2.0,
-                        2.0 * (height + BTN_SPACING) + PLAY_BTN_TOP_PAD,
-                    );
-                    btn.set_width(BTN_WIDTH);
-                },
-            );
+            // the buttons slide in from the left when the menu first opens,
+            // and glide to their new places on any later layout
+            // a button left out of the layout is skipped, along with its place
+            let buttons = MENU_BUTTONS
+                .iter()
+                .filter(|name| self.ui_context.has_element(name))
+                .cloned()
+                .collect::<Vec<_>>();
+            for (i, name) in buttons.into_iter().enumerate() {
+                let slide_in = self.slide_in;
+                self.ui_context.modify_element(
+                    name,
+                    |btn: &mut ui::Button| {
+                        let height = btn.get_height();
+                        let x = screen.w / 2.0;
+                        let y = top + i as f32 * (height + BTN_SPACING) + PLAY_BTN_TOP_PAD;
+
+                        if slide_in {
+                            let slide = ui::Tween::new(
+                                (-btn.get_width(), y),
+                                (x, y),
+                                SLIDE_SECS,
+                                ui::Easing::BackOut,
+                            );
+                            btn.animate_position(slide.with_delay(i as f32 * SLIDE_STAGGER_SECS));
+                        } else {
+                            btn.move_to(x, y, RELAYOUT_SECS, ui::Easing::QuadOut);
+                        }
+                    },
+                );
+            }
+            self.slide_in = false;
         }
 
         Ok(())
@@ -138,14 +168,27 @@ impl event::EventHandler for MenuState {
         xrel: i32,
         yrel: i32,
     ) {
-        if !state.left() {
-            self.ui_context.mouse_moved(x, y);
-        }
+        let input = ui::Input::MouseMotion(state, x, y, xrel, yrel);
+        self.ui_context.handle_input(input, &mut self.info);
     }
 
     fn mouse_button_down_event(&mut self, button: event::MouseButton, x: i32, y: i32) {
-        if button == event::MouseButton::Left {
-            self.ui_context.click(x, y, &mut self.info);
-        }
+        self.ui_context.handle_input(ui::Input::MouseDown(button, x, y), &mut self.info);
+    }
+
+    fn mouse_button_up_event(&mut self, button: event::MouseButton, x: i32, y: i32) {
+        self.ui_context.handle_input(ui::Input::MouseUp(button, x, y), &mut self.info);
+    }
+
+    fn mouse_wheel_event(&mut self, x: i32, y: i32) {
+        self.ui_context.handle_input(ui::Input::Wheel(y), &mut self.info);
+    }
+
+    fn key_down_event(&mut self, keycode: event::Keycode, keymod: event::Mod, repeat: bool) {
+        self.ui_context.handle_input(ui::Input::KeyDown(keycode), &mut self.info);
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: u32, height: u32) {
+        self.info.refresh_ui();
     }
 }
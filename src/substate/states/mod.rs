@@ -2,10 +2,14 @@ mod stateinfo;
 mod menu;
 mod mapgen;
 mod about;
+mod display;
+mod options;
 
 pub use self::menu::MenuState;
 pub use self::mapgen::MapGenState;
 pub use self::about::AboutState;
+pub use self::display::DisplayState;
+pub use self::options::OptionsState;
 
 pub use self::stateinfo::StateInfo;
 pub use self::stateinfo::StoredValue;
@@ -0,0 +1,535 @@
+use std::time::Duration;
+
+use ggez::{GameResult, Context};
+use ggez::event;
+use ggez::event::Keycode;
+use ggez::graphics;
+
+use substate::states::{StateInfo, StoredValue};
+use substate::{Status, SubState};
+use utility::ui;
+use utility::fonts;
+use utility::i18n;
+use utility::settings;
+use utility::settings::{AudioSettings, Settings, KEY_ACTIONS};
+
+const TITLE_TOP_PAD: f32 = 48.0;
+const TITLE_FONT_SIZE: u32 = 32;
+const HEADER_FONT_SIZE: u32 = 20;
+const SECTION_TOP_PAD: f32 = 120.0;
+const SECTION_SPACING: f32 = 12.0;
+const ROW_SPACING: f32 = 32.0;
+const ROW_WIDTH: f32 = 480.0;
+const BTN_WIDTH: f32 = 140.0;
+const SMALL_BTN_WIDTH: f32 = 32.0;
+const BTN_SPACING: f32 = 6.0;
+
+const REBIND_KEY: &'static str = "options_rebind";
+
+// (label, decrease button, increase button) for each volume, in the same
+// order as AudioSettings' fields
+const VOLUME_ROWS: [(&'static str, &'static str, &'static str); 3] = [
+    ("lbl_master_volume", "btn_master_down", "btn_master_up"),
+    ("lbl_music_volume", "btn_music_down", "btn_music_up"),
+    ("lbl_effects_volume", "btn_effects_down", "btn_effects_up"),
+];
+
+// (label, rebind button) for each of settings::KEY_ACTIONS
+const KEY_ROWS: [(&'static str, &'static str); 3] = [
+    ("lbl_key_toggle_debug", "btn_rebind_toggle_debug"),
+    ("lbl_key_regenerate_map", "btn_rebind_regenerate_map"),
+    ("lbl_key_back", "btn_rebind_back"),
+];
+
+// (label, button) for each gameplay option
+const GAMEPLAY_ROWS: [(&'static str, &'static str); 3] = [
+    ("lbl_language", "btn_language"),
+    ("lbl_tooltips", "btn_tooltips"),
+    ("lbl_confirm", "btn_confirm"),
+];
+
+pub struct OptionsState {
+    info: StateInfo,
+    has_initialized_ui: bool,
+    ui_context: ui::UIContext,
+    rebinding: Option<String>,
+}
+
+impl OptionsState {
+    pub fn new() -> OptionsState {
+        OptionsState {
+            info: StateInfo::new(),
+            has_initialized_ui: false,
+            ui_context: ui::UIContext::new(),
+            rebinding: None,
+        }
+    }
+
+    fn init_ui(&mut self, ctx: &mut Context) {
+        graphics::set_background_color(ctx, graphics::BLACK);
+
+        let mut title = ui::Label::localized("options.title");
+        title.set_font(fonts::DEFAULT_FONT_NAME, TITLE_FONT_SIZE);
+        self.ui_context.add_element("lbl_title", Box::new(title));
+
+        let headers = [
+            ("lbl_audio", "options.audio"),
+            ("lbl_display", "options.display"),
+            ("lbl_keys", "options.keys"),
+            ("lbl_gameplay", "options.gameplay"),
+        ];
+        for &(name, key) in headers.iter() {
+            let mut header = ui::Label::localized(key);
+            header.set_font(fonts::DEFAULT_FONT_NAME, HEADER_FONT_SIZE);
+            self.ui_context.add_element(name, Box::new(header));
+        }
+
+        //audio
+        let volume_callbacks: [(fn(&mut StateInfo), fn(&mut StateInfo)); 3] = [
+            (
+                |state: &mut StateInfo| { OptionsState::step_volume(state, 0, -1); },
+                |state: &mut StateInfo| { OptionsState::step_volume(state, 0, 1); },
+            ),
+            (
+                |state: &mut StateInfo| { OptionsState::step_volume(state, 1, -1); },
+                |state: &mut StateInfo| { OptionsState::step_volume(state, 1, 1); },
+            ),
+            (
+                |state: &mut StateInfo| { OptionsState::step_volume(state, 2, -1); },
+                |state: &mut StateInfo| { OptionsState::step_volume(state, 2, 1); },
+            ),
+        ];
+        for (&(label, down, up), &(decrease, increase)) in
+            VOLUME_ROWS.iter().zip(volume_callbacks.iter())
+        {
+            self.ui_context.add_element(
+                label,
+                Box::new(ui::Label::new(String::from("..."))),
+            );
+            self.ui_context.add_element(
+                down,
+                Box::new(ui::Button::new(String::from("-"), decrease)),
+            );
+            self.ui_context.add_element(
+                up,
+                Box::new(ui::Button::new(String::from("+"), increase)),
+            );
+        }
+
+        //display
+        self.ui_context.add_element(
+            "lbl_display_mode",
+            Box::new(ui::Label::new(String::from("..."))),
+        );
+
+        self.ui_context.add_element(
+            "btn_display",
+            Box::new(ui::Button::localized(
+                "options.change",
+                |state: &mut StateInfo| { state.transition("display"); },
+            )),
+        );
+
+        //key bindings
+        let rebind_callbacks: [fn(&mut StateInfo); 3] = [
+            |state: &mut StateInfo| { OptionsState::start_rebind(state, 0); },
+            |state: &mut StateInfo| { OptionsState::start_rebind(state, 1); },
+            |state: &mut StateInfo| { OptionsState::start_rebind(state, 2); },
+        ];
+        for (&(label, button), &callback) in KEY_ROWS.iter().zip(rebind_callbacks.iter()) {
+            self.ui_context.add_element(
+                label,
+                Box::new(ui::Label::new(String::from("..."))),
+            );
+            self.ui_context.add_element(
+                button,
+                Box::new(ui::Button::localized("options.rebind", callback)),
+            );
+        }
+
+        //gameplay
+        self.ui_context.add_element(
+            "lbl_language",
+            Box::new(ui::Label::localized("options.language")),
+        );
+
+        self.ui_context.add_element(
+            "btn_language",
+            Box::new(ui::Button::new(
+                String::from("..."),
+                OptionsState::next_language,
+            )),
+        );
+
+        self.ui_context.add_element(
+            "lbl_tooltips",
+            Box::new(ui::Label::localized("options.tooltips")),
+        );
+
+        self.ui_context.add_element(
+            "btn_tooltips",
+            Box::new(ui::Button::toggle(
+                String::from("..."),
+                |state: &mut StateInfo| {
+                    OptionsState::change(state, |s| {
+                        s.gameplay.show_tooltips = !s.gameplay.show_tooltips;
+                    });
+                },
+            )),
+        );
+
+        self.ui_context.add_element(
+            "lbl_confirm",
+            Box::new(ui::Label::localized("options.confirm_actions")),
+        );
+
+        self.ui_context.add_element(
+            "btn_confirm",
+            Box::new(ui::Button::toggle(
+                String::from("..."),
+                |state: &mut StateInfo| {
+                    OptionsState::change(state, |s| {
+                        s.gameplay.confirm_actions = !s.gameplay.confirm_actions;
+                    });
+                },
+            )),
+        );
+
+        self.ui_context.add_element(
+            "btn_back",
+            Box::new(ui::Button::localized(
+                "options.back",
+                |state: &mut StateInfo| { state.transition("menu"); },
+            )),
+        );
+
+        self.has_initialized_ui = true;
+    }
+
+    // changes the settings and saves them straight away
+    fn change<F>(state: &mut StateInfo, f: F)
+    where
+        F: FnOnce(&mut Settings),
+    {
+        if let Err(why) = settings::update(f) {
+            println!("Unable to save settings: {}", why);
+        }
+        state.refresh_ui();
+    }
+
+    fn volume_mut(audio: &mut AudioSettings, index: usize) -> &mut f32 {
+        match index {
+            0 => &mut audio.master_volume,
+            1 => &mut audio.music_volume,
+            _ => &mut audio.effects_volume,
+        }
+    }
+
+    fn step_volume(state: &mut StateInfo, index: usize, steps: i32) {
+        OptionsState::change(state, |s| {
+            let volume = OptionsState::volume_mut(&mut s.audio, index);
+            *volume = settings::step_volume(*volume, steps);
+        });
+    }
+
+    // the next key pressed is bound to the action; see key_down_event()
+    fn start_rebind(state: &mut StateInfo, index: usize) {
+        state.set_value(
+            REBIND_KEY,
+            StoredValue::Textual { value: String::from(KEY_ACTIONS[index]) },
+        );
+        state.refresh_ui();
+    }
+
+    fn next_language(state: &mut StateInfo) {
+        let locales = i18n::available_locales();
+        let current = i18n::get_locale();
+        let index = locales.iter().position(|l| *l == current).map_or(
+            0,
+            |i| (i + 1) % locales.len(),
+        );
+        let locale = locales[index].clone();
+
+        match i18n::set_locale(&locale) {
+            Ok(()) => OptionsState::change(state, |s| s.gameplay.locale = locale),
+            Err(why) => println!("Unable to switch language: {}", why),
+        }
+    }
+
+    fn place_header(ui_context: &mut ui::UIContext, name: &'static str, screen_w: f32, y: f32) {
+        ui_context.modify_element(
+            name,
+            |lbl: &mut ui::Label| {
+                let width = lbl.get_width();
+                lbl.set_position((screen_w - ROW_WIDTH + width) / 2.0, y);
+            },
+        );
+    }
+
+    fn place_button(ui_context: &mut ui::UIContext, name: &'static str, right: f32, y: f32) {
+        ui_context.modify_element(
+            name,
+            |btn: &mut ui::Button| {
+                btn.set_width(BTN_WIDTH);
+                btn.set_position(right - BTN_WIDTH / 2.0, y);
+            },
+        );
+    }
+
+    fn on_off(value: bool) -> String {
+        if value {
+            i18n::tr("options.on")
+        } else {
+            i18n::tr("options.off")
+        }
+    }
+}
+
+impl SubState for OptionsState {
+    fn get_status(&mut self) -> Option<Status> {
+        self.info.get_status()
+    }
+
+    fn is_capturing_keys(&self) -> bool {
+        self.info.is_capturing_keys()
+    }
+}
+
+impl event::EventHandler for OptionsState {
+    fn update(&mut self, ctx: &mut Context, dt: Duration) -> GameResult<()> {
+        self.ui_context.update(dt);
+
+        //check to see if ui needs to be updated after having been initialized
+        if (self.info.is_ui_dirty() | self.ui_context.is_dirty()) && self.has_initialized_ui {
+            let screen = graphics::get_screen_coordinates(ctx);
+            let current = settings::get();
+
+            if let Some(StoredValue::Textual { value: action }) = self.info.take_value(REBIND_KEY) {
+                self.rebinding = Some(action);
+                self.info.capture_keys(true);
+            }
+
+            // rows have their label on the left and buttons on the right
+            let left = (screen.w - ROW_WIDTH) / 2.0;
+            let right = (screen.w + ROW_WIDTH) / 2.0;
+            let mut y = SECTION_TOP_PAD;
+
+            self.ui_context.modify_element(
+                "lbl_title",
+                |lbl: &mut ui::Label| {
+                    let height = lbl.get_height();
+                    lbl.set_position(screen.w / 2.0, TITLE_TOP_PAD + height / 2.0);
+                },
+            );
+
+            //audio
+            OptionsState::place_header(&mut self.ui_context, "lbl_audio", screen.w, y);
+            y += ROW_SPACING;
+
+            let volumes = [
+                ("options.master_volume", current.audio.master_volume),
+                ("options.music_volume", current.audio.music_volume),
+                ("options.effects_volume", current.audio.effects_volume),
+            ];
+            for (&(label, down, up), &(key, volume)) in VOLUME_ROWS.iter().zip(volumes.iter()) {
+                let percent = ((volume * 100.0).round() as u32).to_string();
+                self.ui_context.modify_element(
+                    label,
+                    |lbl: &mut ui::Label| {
+                        lbl.set_text(i18n::tr_args(key, &[percent.clone()]), ctx);
+                        let width = lbl.get_width();
+                        lbl.set_position(left + width / 2.0, y);
+                    },
+                );
+
+                self.ui_context.modify_element(
+                    down,
+                    |btn: &mut ui::Button| {
+                        btn.set_width(SMALL_BTN_WIDTH);
+                        btn.set_position(
+                            right - SMALL_BTN_WIDTH * 1.5 - BTN_SPACING,
+                            y,
+                        );
+                    },
+                );
+
+                self.ui_context.modify_element(
+                    up,
+                    |btn: &mut ui::Button| {
+                        btn.set_width(SMALL_BTN_WIDTH);
+                        btn.set_position(right - SMALL_BTN_WIDTH / 2.0, y);
+                    },
+                );
+
+                y += ROW_SPACING;
+            }
+
+            //display
+            y += SECTION_SPACING;
+            OptionsState::place_header(&mut self.ui_context, "lbl_display", screen.w, y);
+            y += ROW_SPACING;
+
+            let summary = i18n::tr_args(
+                "options.display_summary",
+                &[
+                    i18n::tr(&format!("display.{}", current.display.mode.name())),
+                    current.display.width.to_string(),
+                    current.display.height.to_string(),
+                ],
+            );
+            self.ui_context.modify_element(
+                "lbl_display_mode",
+                |lbl: &mut ui::Label| {
+                    lbl.set_text(summary.clone(), ctx);
+                    let width = lbl.get_width();
+                    lbl.set_position(left + width / 2.0, y);
+                },
+            );
+            OptionsState::place_button(&mut self.ui_context, "btn_display", right, y);
+            y += ROW_SPACING;
+
+            //key bindings
+            y += SECTION_SPACING;
+            OptionsState::place_header(&mut self.ui_context, "lbl_keys", screen.w, y);
+            y += ROW_SPACING;
+
+            for (&(label, button), action) in KEY_ROWS.iter().zip(KEY_ACTIONS.iter()) {
+                let key_name = if self.rebinding.as_ref().map_or(false, |a| a == action) {
+                    i18n::tr("options.press_key")
+                } else {
+                    match current.keys.get(action) {
+                        Some(keycode) => format!("{:?}", keycode),
+                        None => i18n::tr("options.unbound"),
+                    }
+                };
+                let text = i18n::tr_args(
+                    "options.binding",
+                    &[i18n::tr(&format!("options.action_{}", action)), key_name],
+                );
+
+                self.ui_context.modify_element(
+                    label,
+                    |lbl: &mut ui::Label| {
+                        lbl.set_text(text.clone(), ctx);
+                        let width = lbl.get_width();
+                        lbl.set_position(left + width / 2.0, y);
+                    },
+                );
+                OptionsState::place_button(&mut self.ui_context, button, right, y);
+                y += ROW_SPACING;
+            }
+
+            //gameplay
+            y += SECTION_SPACING;
+            OptionsState::place_header(&mut self.ui_context, "lbl_gameplay", screen.w, y);
+            y += ROW_SPACING;
+
+            let values = [
+                (i18n::tr(&format!("language.{}", i18n::get_locale())), false),
+                (
+                    OptionsState::on_off(current.gameplay.show_tooltips),
+                    current.gameplay.show_tooltips,
+                ),
+                (
+                    OptionsState::on_off(current.gameplay.confirm_actions),
+                    current.gameplay.confirm_actions,
+                ),
+            ];
+            for (&(label, button), &(ref value, checked)) in
+                GAMEPLAY_ROWS.iter().zip(values.iter())
+            {
+                self.ui_context.modify_element(
+                    label,
+                    |lbl: &mut ui::Label| {
+                        let width = lbl.get_width();
+                        lbl.set_position(left + width / 2.0, y);
+                    },
+                );
+
+                self.ui_context.modify_element(
+                    button,
+                    |btn: &mut ui::Button| {
+                        btn.set_text(value.clone());
+                        btn.set_checked(checked);
+                    },
+                );
+                OptionsState::place_button(&mut self.ui_context, button, right, y);
+                y += ROW_SPACING;
+            }
+
+            y += SECTION_SPACING;
+            self.ui_context.modify_element(
+                "btn_back",
+                |btn: &mut ui::Button| {
+                    btn.set_width(BTN_WIDTH);
+                    btn.set_position(screen.w / 2.0, y + ROW_SPACING / 2.0);
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        if !self.has_initialized_ui {
+            self.init_ui(ctx);
+            self.info.refresh_ui();
+        }
+
+        self.ui_context.draw(ctx);
+
+        Ok(())
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        state: event::MouseState,
+        x: i32,
+        y: i32,
+        xrel: i32,
+        yrel: i32,
+    ) {
+        let input = ui::Input::MouseMotion(state, x, y, xrel, yrel);
+        self.ui_context.handle_input(input, &mut self.info);
+    }
+
+    fn mouse_button_down_event(&mut self, button: event::MouseButton, x: i32, y: i32) {
+        self.ui_context.handle_input(ui::Input::MouseDown(button, x, y), &mut self.info);
+    }
+
+    fn mouse_button_up_event(&mut self, button: event::MouseButton, x: i32, y: i32) {
+        self.ui_context.handle_input(ui::Input::MouseUp(button, x, y), &mut self.info);
+    }
+
+    fn mouse_wheel_event(&mut self, x: i32, y: i32) {
+        self.ui_context.handle_input(ui::Input::Wheel(y), &mut self.info);
+    }
+
+    // while waiting on a key to rebind, escape cancels and any other key is
+    // taken for the action
+    fn key_down_event(&mut self, keycode: event::Keycode, keymod: event::Mod, repeat: bool) {
+        if repeat {
+            return;
+        }
+
+        if let Some(action) = self.rebinding.take() {
+            self.info.capture_keys(false);
+            if keycode != Keycode::Escape {
+                OptionsState::change(&mut self.info, |s| s.keys.set(&action, keycode));
+            }
+            self.info.refresh_ui();
+            return;
+        }
+
+        if settings::read(|s| s.keys.matches("back", keycode)) {
+            self.info.transition("menu");
+            return;
+        }
+
+        self.ui_context.handle_input(ui::Input::KeyDown(keycode), &mut self.info);
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: u32, height: u32) {
+        self.info.refresh_ui();
+    }
+}
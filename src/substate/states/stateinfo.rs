@@ -5,6 +5,7 @@ use substate::Status;
 pub enum StoredValue {
     FloatingPoint { value: f32 },
     Unsigned { value: usize },
+    Seed { value: u64 },
     Boolean { value: bool },
     Textual { value: String },
 }
@@ -13,6 +14,8 @@ pub struct StateInfo {
     status: Option<Status>,
     data_store: HashMap<&'static str, StoredValue>,
     should_refresh_ui: bool,
+    actions: Vec<String>,
+    capturing_keys: bool,
 }
 
 impl StateInfo {
@@ -21,6 +24,8 @@ impl StateInfo {
             status: None,
             data_store: HashMap::<&'static str, StoredValue>::new(),
             should_refresh_ui: false,
+            actions: Vec::<String>::new(),
+            capturing_keys: false,
         }
     }
 
@@ -50,6 +55,32 @@ impl StateInfo {
         }
     }
 
+    // removes the value from the store, for one-shot results like dialog choices
+    pub fn take_value(&mut self, key: &'static str) -> Option<StoredValue> {
+        self.data_store.remove(key)
+    }
+
+    // records that an action was asked for, by a button from a layout file
+    // say; the sub-state picks it up with take_actions on its next update
+    pub fn trigger(&mut self, action: &str) {
+        self.actions.push(String::from(action));
+        self.refresh_ui();
+    }
+
+    pub fn take_actions(&mut self) -> Vec<String> {
+        self.actions.drain(..).collect()
+    }
+
+    // while set, every key goes to the sub-state, with no global shortcuts
+    // taken out first; for waiting on a key to bind, say
+    pub fn capture_keys(&mut self, capturing: bool) {
+        self.capturing_keys = capturing;
+    }
+
+    pub fn is_capturing_keys(&self) -> bool {
+        self.capturing_keys
+    }
+
     pub fn refresh_ui(&mut self) {
         self.should_refresh_ui = true;
     }
@@ -42,9 +42,10 @@ impl SubState for TemplateState {
 
 impl event::EventHandler for TemplateState {
     fn update(&mut self, ctx: &mut Context, dt: Duration) -> GameResult<()> {
+        self.ui_context.update(dt);
 
         //check to see if ui needs to be updated after having been initialized
-        if self.info.is_ui_dirty() && self.has_initialized_ui {
+        if (self.info.is_ui_dirty() | self.ui_context.is_dirty()) && self.has_initialized_ui {
             
             // use self.ui_context.modify_element to update control information here
 
@@ -72,14 +73,27 @@ impl event::EventHandler for TemplateState {
         xrel: i32,
         yrel: i32,
     ) {
-        if !state.left() {
-            self.ui_context.mouse_moved(x, y);
-        }
+        let input = ui::Input::MouseMotion(state, x, y, xrel, yrel);
+        self.ui_context.handle_input(input, &mut self.info);
     }
 
     fn mouse_button_down_event(&mut self, button: event::MouseButton, x: i32, y: i32) {
-        if button == event::MouseButton::Left {
-            self.ui_context.click(x, y, &mut self.info);
-        }
+        self.ui_context.handle_input(ui::Input::MouseDown(button, x, y), &mut self.info);
+    }
+
+    fn mouse_button_up_event(&mut self, button: event::MouseButton, x: i32, y: i32) {
+        self.ui_context.handle_input(ui::Input::MouseUp(button, x, y), &mut self.info);
+    }
+
+    fn mouse_wheel_event(&mut self, x: i32, y: i32) {
+        self.ui_context.handle_input(ui::Input::Wheel(y), &mut self.info);
+    }
+
+    fn key_down_event(&mut self, keycode: event::Keycode, keymod: event::Mod, repeat: bool) {
+        self.ui_context.handle_input(ui::Input::KeyDown(keycode), &mut self.info);
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: u32, height: u32) {
+        self.info.refresh_ui();
     }
 }
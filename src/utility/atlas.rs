@@ -0,0 +1,278 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+use ggez::Context;
+use ggez::graphics;
+use ggez::graphics::{Color, DrawParam, Image, Point, Rect};
+use serde_json;
+
+pub const ATLAS_FILE: &'static str = "resources/ui_atlas.json";
+
+// a named part of the atlas image, in pixels from its top-left corner; a
+// region with a slice is drawn as a nine-slice, its (left, top, right,
+// bottom) insets keeping their size while the middle stretches
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+    #[serde(default)]
+    pub slice: Option<[u32; 4]>,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct AtlasDef {
+    // a path inside the ggez resources directory
+    pub image: String,
+    #[serde(default)]
+    pub regions: HashMap<String, Region>,
+}
+
+impl AtlasDef {
+    pub fn from_json(contents: &str) -> Result<AtlasDef, String> {
+        serde_json::from_str(contents).map_err(|e| format!("Invalid atlas: {}", e))
+    }
+
+    pub fn from_file(path: &str) -> Result<AtlasDef, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut contents = String::new();
+        BufReader::new(file).read_to_string(&mut contents).map_err(
+            |e| {
+                format!("{}: {}", path, e)
+            },
+        )?;
+        AtlasDef::from_json(&contents)
+    }
+}
+
+// one piece of a region as it's drawn: the source rectangle in pixels
+// (top-left based) and where it goes on screen (center based)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Piece {
+    pub src: Rect,
+    pub dest: Rect,
+}
+
+// splits a region up to fill the rectangle; insets too big for it shrink
+// in proportion to each other
+pub fn nine_slice(region: &Region, rect: &Rect) -> Vec<Piece> {
+    let slice = region.slice.unwrap_or([0, 0, 0, 0]);
+    let (left, top, right, bottom) = (slice[0], slice[1], slice[2], slice[3]);
+    let fit = |a: u32, b: u32, size: f32| -> (f32, f32) {
+        let (a, b) = (a as f32, b as f32);
+        if a + b > size && a + b > 0.0 {
+            (size * a / (a + b), size * b / (a + b))
+        } else {
+            (a, b)
+        }
+    };
+    let (dl, dr) = fit(left, right, rect.w);
+    let (dt, db) = fit(top, bottom, rect.h);
+
+    let rx = region.x as f32;
+    let ry = region.y as f32;
+    let src_cols = [
+        rx,
+        rx + left as f32,
+        rx + (region.w - right) as f32,
+        rx + region.w as f32,
+    ];
+    let src_rows = [
+        ry,
+        ry + top as f32,
+        ry + (region.h - bottom) as f32,
+        ry + region.h as f32,
+    ];
+
+    let x0 = rect.x - rect.w / 2.0;
+    let y0 = rect.y - rect.h / 2.0;
+    let dest_cols = [x0, x0 + dl, x0 + rect.w - dr, x0 + rect.w];
+    let dest_rows = [y0, y0 + dt, y0 + rect.h - db, y0 + rect.h];
+
+    let mut pieces = Vec::<Piece>::new();
+    for row in 0..3 {
+        for col in 0..3 {
+            let sw = src_cols[col + 1] - src_cols[col];
+            let sh = src_rows[row + 1] - src_rows[row];
+            let dw = dest_cols[col + 1] - dest_cols[col];
+            let dh = dest_rows[row + 1] - dest_rows[row];
+            if sw <= 0.0 || sh <= 0.0 || dw <= 0.0 || dh <= 0.0 {
+                continue;
+            }
+
+            pieces.push(Piece {
+                src: Rect::new(src_cols[col], src_rows[row], sw, sh),
+                dest: Rect::new(dest_cols[col] + dw / 2.0, dest_rows[row] + dh / 2.0, dw, dh),
+            });
+        }
+    }
+    pieces
+}
+
+struct Atlas {
+    def: Option<AtlasDef>,
+    image: Option<Image>,
+    image_failed: bool,
+}
+
+impl Atlas {
+    fn new() -> Atlas {
+        Atlas {
+            def: None,
+            image: None,
+            image_failed: false,
+        }
+    }
+
+    fn def(&mut self) -> &AtlasDef {
+        if self.def.is_none() {
+            let def = AtlasDef::from_file(ATLAS_FILE).unwrap_or_else(|why| {
+                println!("Unable to load the texture atlas: {}", why);
+                AtlasDef {
+                    image: String::new(),
+                    regions: HashMap::<String, Region>::new(),
+                }
+            });
+            self.def = Some(def);
+        }
+        self.def.as_ref().unwrap()
+    }
+
+    fn image(&mut self, ctx: &mut Context) -> Option<&Image> {
+        if self.image.is_none() && !self.image_failed {
+            let path = self.def().image.clone();
+            match Image::new(ctx, &path) {
+                Ok(mut image) => {
+                    image.set_filter(graphics::FilterMode::Nearest);
+                    self.image = Some(image);
+                }
+                Err(why) => {
+                    println!("Unable to load the atlas image {}: {}", path, why);
+                    self.image_failed = true;
+                }
+            }
+        }
+        self.image.as_ref()
+    }
+}
+
+// ggez images can't be shared between threads, so the atlas belongs to the
+// one drawing everything
+thread_local! {
+    static ATLAS: RefCell<Atlas> = RefCell::new(Atlas::new());
+}
+
+pub fn get_region(name: &str) -> Option<Region> {
+    ATLAS.with(|atlas| atlas.borrow_mut().def().regions.get(name).cloned())
+}
+
+// draws a region stretched (or nine-sliced) over the rectangle, tinted by the
+// color; false if there's no such region, so the caller can draw something
+// else instead
+pub fn draw(ctx: &mut Context, name: &str, rect: &Rect, color: Color) -> bool {
+    let region = match get_region(name) {
+        Some(region) => region,
+        None => return false,
+    };
+
+    ATLAS.with(|atlas| {
+        let mut atlas = atlas.borrow_mut();
+        let image = match atlas.image(ctx) {
+            Some(image) => image.clone(),
+            None => return false,
+        };
+        let (iw, ih) = (image.width() as f32, image.height() as f32);
+
+        graphics::set_color(ctx, color).unwrap();
+        for piece in nine_slice(&region, rect) {
+            let param = DrawParam {
+                src: Rect::new(
+                    piece.src.x / iw,
+                    piece.src.y / ih,
+                    piece.src.w / iw,
+                    piece.src.h / ih,
+                ),
+                dest: Point::new(piece.dest.x, piece.dest.y),
+                scale: Point::new(piece.dest.w / piece.src.w, piece.dest.h / piece.src.h),
+                ..Default::default()
+            };
+            graphics::draw_ex(ctx, &image, param).unwrap();
+        }
+        true
+    })
+}
+
+// forgets the loaded atlas so that it's read again on the next draw
+pub fn reload() {
+    ATLAS.with(|atlas| *atlas.borrow_mut() = Atlas::new());
+}
+
+#[cfg(test)]
+mod test {
+    use ggez::graphics::Rect;
+
+    use utility::theme::Theme;
+    use super::{nine_slice, AtlasDef, Region, ATLAS_FILE};
+
+    #[test]
+    fn nine_slices_keep_their_corners() {
+        let region = Region {
+            x: 16,
+            y: 0,
+            w: 16,
+            h: 16,
+            slice: Some([4, 4, 4, 4]),
+        };
+        let pieces = nine_slice(&region, &Rect::new(50.0, 20.0, 100.0, 40.0));
+        assert_eq!(pieces.len(), 9);
+
+        //top-left corner, unstretched
+        assert_eq!(pieces[0].src, Rect::new(16.0, 0.0, 4.0, 4.0));
+        assert_eq!(pieces[0].dest, Rect::new(2.0, 2.0, 4.0, 4.0));
+
+        //middle, stretched both ways
+        assert_eq!(pieces[4].src, Rect::new(20.0, 4.0, 8.0, 8.0));
+        assert_eq!(pieces[4].dest, Rect::new(50.0, 20.0, 92.0, 32.0));
+
+        //insets shrink when the rectangle is too small for them
+        let pieces = nine_slice(&region, &Rect::new(3.0, 3.0, 6.0, 6.0));
+        assert_eq!(pieces.len(), 4);
+        assert_eq!(pieces[0].dest, Rect::new(1.5, 1.5, 3.0, 3.0));
+    }
+
+    #[test]
+    fn regions_without_slices_are_one_piece() {
+        let region = Region {
+            x: 0,
+            y: 16,
+            w: 16,
+            h: 16,
+            slice: None,
+        };
+        let pieces = nine_slice(&region, &Rect::new(0.0, 0.0, 48.0, 48.0));
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(pieces[0].src, Rect::new(0.0, 16.0, 16.0, 16.0));
+    }
+
+    #[test]
+    fn themed_images_exist_in_the_atlas() {
+        let atlas = AtlasDef::from_file(ATLAS_FILE).unwrap();
+        let theme = Theme::from_file(::utility::theme::THEME_FILE).unwrap();
+
+        for widget in ["button", "dialog"].iter() {
+            let style = theme.get_style(widget, "");
+            let images = [&style.image, &style.image_hover, &style.image_pressed];
+            for image in images.iter().filter_map(|i| i.as_ref()) {
+                assert!(
+                    atlas.regions.contains_key(image),
+                    "Image \"{}\" used by {} is missing from the atlas",
+                    image,
+                    widget
+                );
+            }
+        }
+    }
+}
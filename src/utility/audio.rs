@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use ggez::audio::SoundData;
+use rodio;
+use rodio::{Decoder, Endpoint, Sink};
+use rodio::Source;
+
+use utility::settings;
+use utility::settings::AudioSettings;
+
+pub const SOUND_HOVER: &'static str = "ui_hover";
+pub const SOUND_CLICK: &'static str = "ui_click";
+
+const AUDIO_DIR: &'static str = "resources/audio/";
+const EFFECTS: [&'static str; 2] = [SOUND_HOVER, SOUND_CLICK];
+const MUSIC: [&'static str; 2] = ["music_menu", "music_mapgen"];
+
+// how long one track takes to fade into the next
+const CROSSFADE_SECS: f32 = 1.5;
+
+lazy_static! {
+    static ref PENDING_EFFECTS: Mutex<Vec<&'static str>> = Mutex::new(Vec::<&'static str>::new());
+}
+
+// queues a sound effect; anything without access to the audio manager (UI
+// elements, mostly) goes through here, and the queue is emptied each update
+pub fn play_effect(name: &'static str) {
+    PENDING_EFFECTS.lock().unwrap().push(name);
+}
+
+pub fn music_volume(audio: &AudioSettings) -> f32 {
+    audio.master_volume * audio.music_volume
+}
+
+pub fn effects_volume(audio: &AudioSettings) -> f32 {
+    audio.master_volume * audio.effects_volume
+}
+
+// the backend for the default audio device, or one that stays quiet if
+// there isn't one
+pub fn default_backend() -> Box<AudioBackend> {
+    match RodioBackend::new() {
+        Some(backend) => Box::new(backend),
+        None => {
+            println!("Unable to find an audio device, playing without sound");
+            Box::new(NullBackend::new())
+        }
+    }
+}
+
+// whatever actually makes the noise; volumes are final, with the settings
+// and any fading already applied
+pub trait AudioBackend {
+    fn play_effect(&mut self, name: &str, volume: f32);
+    fn start_music(&mut self, name: &str, volume: f32);
+    fn set_music_volume(&mut self, name: &str, volume: f32);
+    fn stop_music(&mut self, name: &str);
+}
+
+// plays sounds from resources/audio/, all of which are loaded up front
+pub struct RodioBackend {
+    endpoint: Endpoint,
+    effects: HashMap<String, SoundData>,
+    music: HashMap<String, SoundData>,
+    // one sink per track that's been started, looping it forever
+    tracks: HashMap<String, Sink>,
+}
+
+impl RodioBackend {
+    // this version of rodio only re-exports cpal's old name for the default
+    // endpoint
+    #[allow(deprecated)]
+    pub fn new() -> Option<RodioBackend> {
+        rodio::get_default_endpoint().map(|endpoint| {
+            RodioBackend {
+                endpoint: endpoint,
+                effects: RodioBackend::load_all(&EFFECTS),
+                music: RodioBackend::load_all(&MUSIC),
+                tracks: HashMap::<String, Sink>::new(),
+            }
+        })
+    }
+
+    fn load_all(names: &[&'static str]) -> HashMap<String, SoundData> {
+        let mut sounds = HashMap::<String, SoundData>::new();
+        for name in names.iter() {
+            match RodioBackend::load(name) {
+                Ok(sound) => {
+                    sounds.insert(String::from(*name), sound);
+                }
+                Err(why) => println!("Unable to load sound: {}", why),
+            }
+        }
+        sounds
+    }
+
+    fn load(name: &str) -> Result<SoundData, String> {
+        let path = format!("{}{}.wav", AUDIO_DIR, name);
+        let file = File::open(&path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut buffer = Vec::<u8>::new();
+        BufReader::new(file).read_to_end(&mut buffer).map_err(
+            |e| {
+                format!("{}: {}", path, e)
+            },
+        )?;
+        // checks the data can be played now rather than the first time it's
+        // needed
+        let sound = SoundData::from(buffer);
+        Decoder::new(Cursor::new(sound.clone())).map_err(
+            |e| format!("{}: {}", path, e),
+        )?;
+        Ok(sound)
+    }
+
+    fn sink(&self, volume: f32) -> Sink {
+        let mut sink = Sink::new(&self.endpoint);
+        sink.set_volume(volume);
+        sink
+    }
+}
+
+impl AudioBackend for RodioBackend {
+    // every play gets a sink of its own, so quick repeats overlap rather than
+    // queueing up behind each other
+    fn play_effect(&mut self, name: &str, volume: f32) {
+        if let Some(sound) = self.effects.get(name) {
+            match Decoder::new(Cursor::new(sound.clone())) {
+                Ok(decoder) => {
+                    let sink = self.sink(volume);
+                    sink.append(decoder);
+                    sink.detach();
+                }
+                Err(why) => println!("Unable to play {}: {}", name, why),
+            }
+        }
+    }
+
+    // a track that was stopped before picks up where it left off
+    fn start_music(&mut self, name: &str, volume: f32) {
+        if let Some(sink) = self.tracks.get_mut(name) {
+            sink.set_volume(volume);
+            sink.play();
+            return;
+        }
+
+        if let Some(sound) = self.music.get(name) {
+            match Decoder::new(Cursor::new(sound.clone())) {
+                Ok(decoder) => {
+                    let sink = self.sink(volume);
+                    sink.append(decoder.repeat_infinite());
+                    self.tracks.insert(String::from(name), sink);
+                }
+                Err(why) => println!("Unable to play {}: {}", name, why),
+            }
+        }
+    }
+
+    fn set_music_volume(&mut self, name: &str, volume: f32) {
+        if let Some(sink) = self.tracks.get_mut(name) {
+            sink.set_volume(volume);
+        }
+    }
+
+    fn stop_music(&mut self, name: &str) {
+        if let Some(sink) = self.tracks.get_mut(name) {
+            sink.pause();
+            sink.set_volume(0.0);
+        }
+    }
+}
+
+// keeps track of what it was asked to do instead of playing anything, for
+// running headless
+pub struct NullBackend {
+    pub effects: Vec<(String, f32)>,
+    pub music: HashMap<String, f32>,
+}
+
+impl NullBackend {
+    pub fn new() -> NullBackend {
+        NullBackend {
+            effects: Vec::<(String, f32)>::new(),
+            music: HashMap::<String, f32>::new(),
+        }
+    }
+}
+
+impl AudioBackend for NullBackend {
+    fn play_effect(&mut self, name: &str, volume: f32) {
+        self.effects.push((String::from(name), volume));
+    }
+
+    fn start_music(&mut self, name: &str, volume: f32) {
+        self.music.insert(String::from(name), volume);
+    }
+
+    fn set_music_volume(&mut self, name: &str, volume: f32) {
+        if let Some(v) = self.music.get_mut(name) {
+            *v = volume;
+        }
+    }
+
+    fn stop_music(&mut self, name: &str) {
+        self.music.remove(name);
+    }
+}
+
+struct Track {
+    name: &'static str,
+    fade: f32,
+}
+
+// plays queued sound effects and crossfades between music tracks, applying
+// the volume settings to both
+pub struct AudioManager {
+    backend: Box<AudioBackend>,
+    current: Option<Track>,
+    fading: Vec<Track>,
+}
+
+impl AudioManager {
+    pub fn new(backend: Box<AudioBackend>) -> AudioManager {
+        AudioManager {
+            backend: backend,
+            current: None,
+            fading: Vec::<Track>::new(),
+        }
+    }
+
+    // fades whatever is playing out and the given track in; asking for the
+    // track that's already playing does nothing
+    pub fn play_music(&mut self, name: Option<&'static str>) {
+        if self.current.as_ref().map(|t| t.name) == name {
+            return;
+        }
+
+        if let Some(track) = self.current.take() {
+            self.fading.push(track);
+        }
+
+        if let Some(name) = name {
+            // a track that was on its way out fades back in from where it was
+            let fade = match self.fading.iter().position(|t| t.name == name) {
+                Some(i) => self.fading.remove(i).fade,
+                None => {
+                    self.backend.start_music(name, 0.0);
+                    0.0
+                }
+            };
+            self.current = Some(Track {
+                name: name,
+                fade: fade,
+            });
+        }
+    }
+
+    pub fn current_music(&self) -> Option<&'static str> {
+        self.current.as_ref().map(|t| t.name)
+    }
+
+    pub fn update(&mut self, dt: Duration) {
+        let audio = settings::read(|s| s.audio.clone());
+        let pending = PENDING_EFFECTS.lock().unwrap().drain(..).collect::<Vec<_>>();
+        self.play_effects(&pending, &audio);
+        self.update_music(dt, &audio);
+    }
+
+    fn play_effects(&mut self, names: &[&'static str], audio: &AudioSettings) {
+        let volume = effects_volume(audio);
+        for name in names.iter() {
+            self.backend.play_effect(name, volume);
+        }
+    }
+
+    fn update_music(&mut self, dt: Duration, audio: &AudioSettings) {
+        let step = (dt.as_secs() as f32 + dt.subsec_nanos() as f32 / 1_000_000_000.0) /
+            CROSSFADE_SECS;
+        let music = music_volume(audio);
+
+        if let Some(ref mut track) = self.current {
+            track.fade = (track.fade + step).min(1.0);
+            self.backend.set_music_volume(track.name, track.fade * music);
+        }
+
+        for track in self.fading.iter_mut() {
+            track.fade = (track.fade - step).max(0.0);
+            self.backend.set_music_volume(track.name, track.fade * music);
+        }
+
+        let backend = &mut self.backend;
+        self.fading.retain(|track| if track.fade > 0.0 {
+            true
+        } else {
+            backend.stop_music(track.name);
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use utility::settings::AudioSettings;
+    use super::{AudioManager, AudioBackend, NullBackend, SOUND_CLICK};
+
+    // lets a test look at the backend after handing it to the manager
+    struct SharedBackend(Rc<RefCell<NullBackend>>);
+
+    impl AudioBackend for SharedBackend {
+        fn play_effect(&mut self, name: &str, volume: f32) {
+            self.0.borrow_mut().play_effect(name, volume);
+        }
+        fn start_music(&mut self, name: &str, volume: f32) {
+            self.0.borrow_mut().start_music(name, volume);
+        }
+        fn set_music_volume(&mut self, name: &str, volume: f32) {
+            self.0.borrow_mut().set_music_volume(name, volume);
+        }
+        fn stop_music(&mut self, name: &str) {
+            self.0.borrow_mut().stop_music(name);
+        }
+    }
+
+    fn manager() -> (AudioManager, Rc<RefCell<NullBackend>>) {
+        let backend = Rc::new(RefCell::new(NullBackend::new()));
+        let manager = AudioManager::new(Box::new(SharedBackend(backend.clone())));
+        (manager, backend)
+    }
+
+    #[test]
+    fn music_crossfades_between_tracks() {
+        let audio = AudioSettings {
+            master_volume: 1.0,
+            music_volume: 0.5,
+            effects_volume: 1.0,
+        };
+        let (mut manager, backend) = manager();
+
+        manager.play_music(Some("a"));
+        manager.update_music(Duration::from_secs(5), &audio);
+        assert_eq!(backend.borrow().music["a"], 0.5);
+
+        manager.play_music(Some("b"));
+        manager.update_music(Duration::from_millis(750), &audio);
+        assert!((backend.borrow().music["a"] - 0.25).abs() < 0.0001);
+        assert!((backend.borrow().music["b"] - 0.25).abs() < 0.0001);
+
+        //once faded out, the old track is stopped
+        manager.update_music(Duration::from_secs(5), &audio);
+        assert!(!backend.borrow().music.contains_key("a"));
+        assert_eq!(manager.current_music(), Some("b"));
+
+        manager.play_music(None);
+        manager.update_music(Duration::from_secs(5), &audio);
+        assert!(backend.borrow().music.is_empty());
+    }
+
+    #[test]
+    fn volumes_are_scaled_by_the_master_volume() {
+        let audio = AudioSettings {
+            master_volume: 0.5,
+            music_volume: 0.5,
+            effects_volume: 0.8,
+        };
+        let (mut manager, backend) = manager();
+
+        manager.play_effects(&[SOUND_CLICK], &audio);
+        assert_eq!(backend.borrow().effects, vec![(String::from(SOUND_CLICK), 0.4)]);
+
+        manager.play_music(Some("a"));
+        manager.update_music(Duration::from_secs(5), &audio);
+        assert_eq!(backend.borrow().music["a"], 0.25);
+    }
+}
@@ -1,22 +1,17 @@
 use ggez;
 use ggez::Context;
 use ggez::graphics;
-use ggez::graphics::{Point, Font, Text};
+use ggez::graphics::{Point, Text};
 
-const DEBUG_FONT_FILE: &'static [u8; 79_584] = include_bytes!("../embedded/LessPerfectDOSVGA.ttf");
-const DEBUG_FONT_SIZE: u32 = 16;
+use utility::fonts;
 
-lazy_static! {
-    static ref DEBUG_FONT: Font = Font::from_bytes("LessPerfectDOSVGA",
-                                                    DEBUG_FONT_FILE,
-                                                    DEBUG_FONT_SIZE,
-                                                    (72.0, 72.0)).unwrap();
-}
+const DEBUG_FONT_SIZE: u32 = 16;
 
 pub fn draw_debug_information(ctx: &mut Context) {
     //draw current FPS
     let fps_str = format!("{:.1}", ggez::timer::get_fps(ctx));
-    let fps_text = Text::new(ctx, fps_str.as_str(), &(*DEBUG_FONT)).unwrap();
+    let font = fonts::get_font(fonts::DEBUG_FONT_NAME, DEBUG_FONT_SIZE);
+    let fps_text = Text::new(ctx, fps_str.as_str(), &font).unwrap();
     let fps_pos = Point::new(
         fps_text.width() as f32 / 2.0,
         fps_text.height() as f32 / 2.0,
@@ -49,7 +44,8 @@ pub fn draw_debug_information(ctx: &mut Context) {
 
 pub fn draw_mouse_position(x: i32, y: i32, ctx: &mut Context) {
     let mouse_pos_str = format!("X: {}, Y: {}", x, y);
-    let mouse_pos_text = Text::new(ctx, mouse_pos_str.as_str(), &(*DEBUG_FONT)).unwrap();
+    let font = fonts::get_font(fonts::DEBUG_FONT_NAME, DEBUG_FONT_SIZE);
+    let mouse_pos_text = Text::new(ctx, mouse_pos_str.as_str(), &font).unwrap();
     let mouse_pos_pos = Point::new(
         mouse_pos_text.width() as f32 / 2.0,
         24.0 + mouse_pos_text.height() as f32 / 2.0,
@@ -0,0 +1,75 @@
+use ggez::{GameResult, Context};
+use ggez::graphics;
+use ggez::graphics::{WindowMode, FullscreenType};
+
+use utility::settings::{DisplaySettings, DisplayMode};
+
+// resolutions smaller than this aren't offered
+const MIN_RESOLUTION: (u32, u32) = (800, 600);
+
+pub fn window_mode_for(settings: &DisplaySettings) -> WindowMode {
+    let (borderless, fullscreen_type) = match settings.mode {
+        DisplayMode::Windowed => (false, FullscreenType::Off),
+        DisplayMode::Fullscreen => (false, FullscreenType::True),
+        DisplayMode::Borderless => (true, FullscreenType::Desktop),
+    };
+
+    WindowMode {
+        borderless: borderless,
+        fullscreen_type: fullscreen_type,
+        vsync: settings.vsync,
+        ..Default::default()
+    }
+}
+
+// changes the window to match the settings; set_mode doesn't raise a resize
+// event, so MainState picks the new size up on its next update instead
+pub fn apply(ctx: &mut Context, settings: &DisplaySettings) -> GameResult<()> {
+    graphics::set_mode(
+        ctx,
+        settings.width,
+        settings.height,
+        window_mode_for(settings),
+    )
+}
+
+pub fn window_size(ctx: &mut Context) -> (u32, u32) {
+    ctx.gfx_context.get_window().size()
+}
+
+// the first display's modes, largest first and without duplicates (modes
+// that only differ by refresh rate show up more than once)
+pub fn available_resolutions(ctx: &mut Context) -> Vec<(u32, u32)> {
+    let modes = graphics::get_fullscreen_modes(ctx, 0).unwrap_or_else(|why| {
+        println!("Unable to list display modes: {}", why);
+        Vec::<(u32, u32)>::new()
+    });
+    filter_resolutions(modes)
+}
+
+pub fn filter_resolutions(mut modes: Vec<(u32, u32)>) -> Vec<(u32, u32)> {
+    modes.retain(|&(w, h)| w >= MIN_RESOLUTION.0 && h >= MIN_RESOLUTION.1);
+    modes.sort_by(|a, b| b.cmp(a));
+    modes.dedup();
+    modes
+}
+
+#[cfg(test)]
+mod test {
+    use super::filter_resolutions;
+
+    #[test]
+    fn resolutions_are_sorted_and_deduplicated() {
+        let modes = vec![
+            (1280, 720),
+            (1920, 1080),
+            (640, 480),
+            (1920, 1080),
+            (1280, 1024),
+        ];
+        assert_eq!(
+            filter_resolutions(modes),
+            vec![(1920, 1080), (1280, 1024), (1280, 720)]
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use std::ffi::OsString;
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// writes to a file beside the real one and then swaps it in, so that a crash
+// part way through leaves the old file as it was rather than half written
+pub fn write_atomically(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let mut temp_name = OsString::from(path.as_os_str());
+    temp_name.push(".tmp");
+    let temp_path = PathBuf::from(temp_name);
+
+    let written = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+
+    written.map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        format!("{}: {}", path.display(), e)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+
+    use super::write_atomically;
+
+    #[test]
+    fn files_are_replaced_whole() {
+        let dir = env::temp_dir().join("roguelike_write_atomically");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.txt");
+
+        write_atomically(&path, b"first").unwrap();
+        write_atomically(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        //nothing is left behind when the file can't be written
+        assert!(write_atomically(&dir.join("missing").join("test.txt"), b"").is_err());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::sync::{Arc, Mutex};
+
+use ggez::graphics::Font;
+
+use utility::theme::Style;
+
+pub const DEFAULT_FONT_NAME: &'static str = "default";
+pub const DEBUG_FONT_NAME: &'static str = "debug";
+pub const DEFAULT_FONT_SIZE: u32 = 16;
+const FONT_DIR: &'static str = "resources/fonts/";
+const FONT_DPI: (f32, f32) = (96.0, 96.0);
+
+const DEFAULT_FONT_FILE: &'static [u8; 95_068] =
+    include_bytes!("../embedded/NunitoSans-SemiBold.ttf");
+const DEBUG_FONT_FILE: &'static [u8; 79_584] = include_bytes!("../embedded/LessPerfectDOSVGA.ttf");
+
+lazy_static! {
+    static ref FONT_MANAGER: Mutex<FontManager> = Mutex::new(FontManager::new());
+}
+
+struct FontSource {
+    bytes: Vec<u8>,
+    dpi: (f32, f32),
+}
+
+// keeps the raw data for every known font around and lazily rasterizes them at
+// whatever sizes are asked for, so each (name, size) pair is only built once
+pub struct FontManager {
+    sources: HashMap<String, FontSource>,
+    cache: HashMap<(String, u32), Arc<Font>>,
+}
+
+impl FontManager {
+    fn new() -> FontManager {
+        let mut manager = FontManager {
+            sources: HashMap::<String, FontSource>::new(),
+            cache: HashMap::<(String, u32), Arc<Font>>::new(),
+        };
+
+        manager.register(DEFAULT_FONT_NAME, DEFAULT_FONT_FILE.to_vec(), FONT_DPI);
+        manager.register(DEBUG_FONT_NAME, DEBUG_FONT_FILE.to_vec(), (72.0, 72.0));
+
+        manager
+    }
+
+    fn register(&mut self, name: &str, bytes: Vec<u8>, dpi: (f32, f32)) {
+        self.sources.insert(
+            String::from(name),
+            FontSource {
+                bytes: bytes,
+                dpi: dpi,
+            },
+        );
+        self.cache.retain(|key, _| key.0 != name);
+    }
+
+    fn load_file(&mut self, name: &str) -> Result<(), String> {
+        let path = format!("{}{}.ttf", FONT_DIR, name);
+        let file = File::open(&path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut buffer = Vec::<u8>::new();
+        BufReader::new(file).read_to_end(&mut buffer).map_err(
+            |e| {
+                format!("{}: {}", path, e)
+            },
+        )?;
+        self.register(name, buffer, FONT_DPI);
+        Ok(())
+    }
+
+    fn get(&mut self, name: &str, size: u32) -> Arc<Font> {
+        let key = (String::from(name), size);
+        if let Some(font) = self.cache.get(&key) {
+            return font.clone();
+        }
+
+        if !self.sources.contains_key(name) {
+            if let Err(why) = self.load_file(name) {
+                println!("Unable to load font \"{}\", falling back to default: {}", name, why);
+                return self.get_fallback(name, size);
+            }
+        }
+
+        let font = {
+            let source = &self.sources[name];
+            Font::from_bytes(name, &source.bytes, size, source.dpi)
+        };
+
+        match font {
+            Ok(font) => {
+                let font = Arc::new(font);
+                self.cache.insert(key, font.clone());
+                font
+            }
+            Err(why) => {
+                println!("Unable to build font \"{}\", falling back to default: {}", name, why);
+                self.get_fallback(name, size)
+            }
+        }
+    }
+
+    fn get_fallback(&mut self, name: &str, size: u32) -> Arc<Font> {
+        if name == DEFAULT_FONT_NAME {
+            panic!("The embedded default font could not be loaded!");
+        }
+
+        // remember the substitution so the failed lookup isn't repeated every frame
+        let font = self.get(DEFAULT_FONT_NAME, size);
+        self.cache.insert((String::from(name), size), font.clone());
+        font
+    }
+}
+
+pub fn get_font(name: &str, size: u32) -> Arc<Font> {
+    FONT_MANAGER.lock().unwrap().get(name, size)
+}
+
+// loads (or re-loads) a font from the fonts directory under the given name
+pub fn load_font_file(name: &str) -> Result<(), String> {
+    FONT_MANAGER.lock().unwrap().load_file(name)
+}
+
+// the font a single UI element wants to draw with; an explicitly-chosen name
+// or size wins over whatever the element's theme style asks for
+pub struct FontSelection {
+    name: Option<String>,
+    size: Option<u32>,
+    resolved: Option<(String, u32, Arc<Font>)>,
+}
+
+impl FontSelection {
+    pub fn new() -> FontSelection {
+        FontSelection {
+            name: None,
+            size: None,
+            resolved: None,
+        }
+    }
+
+    pub fn set(&mut self, name: &str, size: u32) {
+        self.name = Some(String::from(name));
+        self.size = Some(size);
+        self.resolved = None;
+    }
+
+    // returns true if the resolved font changed, meaning any text rendered with
+    // the previous font needs to be rebuilt
+    pub fn update(&mut self, style: &Style) -> bool {
+        let name = self.name.clone().unwrap_or_else(|| style.font.clone());
+        let size = self.size.unwrap_or(style.font_size);
+        self.resolve(name, size)
+    }
+
+    pub fn get(&mut self) -> Arc<Font> {
+        if self.resolved.is_none() {
+            let name = self.name.clone().unwrap_or_else(
+                || String::from(DEFAULT_FONT_NAME),
+            );
+            let size = self.size.unwrap_or(DEFAULT_FONT_SIZE);
+            self.resolve(name, size);
+        }
+
+        match self.resolved {
+            Some((_, _, ref font)) => font.clone(),
+            None => unreachable!(),
+        }
+    }
+
+    fn resolve(&mut self, name: String, size: u32) -> bool {
+        if let Some((ref current_name, current_size, _)) = self.resolved {
+            if *current_name == name && current_size == size {
+                return false;
+            }
+        }
+
+        let font = get_font(&name, size);
+        self.resolved = Some((name, size, font));
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use super::{get_font, DEFAULT_FONT_NAME};
+
+    #[test]
+    fn fonts_are_cached_by_name_and_size() {
+        let a = get_font(DEFAULT_FONT_NAME, 20);
+        let b = get_font(DEFAULT_FONT_NAME, 20);
+        let c = get_font(DEFAULT_FONT_NAME, 24);
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+    }
+
+    #[test]
+    fn unknown_fonts_fall_back_to_the_default() {
+        let fallback = get_font("no-such-font", 18);
+        assert!(Arc::ptr_eq(&fallback, &get_font(DEFAULT_FONT_NAME, 18)));
+    }
+}
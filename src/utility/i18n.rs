@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::sync::RwLock;
+
+use serde_json;
+
+pub const DEFAULT_LOCALE: &'static str = "en";
+const LOCALE_DIR: &'static str = "resources/locale/";
+
+// the default locale is compiled in so that every lookup has something to
+// fall back on, even when the resources directory is missing
+const DEFAULT_LOCALE_FILE: &'static str = include_str!("../../resources/locale/en.json");
+
+lazy_static! {
+    static ref DEFAULT_STRINGS: HashMap<String, String> =
+        parse_string_table(DEFAULT_LOCALE_FILE).unwrap();
+    static ref STRINGS: RwLock<StringTable> = RwLock::new(StringTable::new());
+}
+
+struct StringTable {
+    locale: String,
+    strings: HashMap<String, String>,
+    generation: usize,
+}
+
+impl StringTable {
+    fn new() -> StringTable {
+        StringTable {
+            locale: String::from(DEFAULT_LOCALE),
+            strings: HashMap::<String, String>::new(),
+            generation: 0,
+        }
+    }
+}
+
+pub fn parse_string_table(contents: &str) -> Result<HashMap<String, String>, String> {
+    serde_json::from_str(contents).map_err(|e| format!("Invalid string table: {}", e))
+}
+
+fn load_string_table(locale: &str) -> Result<HashMap<String, String>, String> {
+    let path = format!("{}{}.json", LOCALE_DIR, locale);
+    let file = File::open(&path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut contents = String::new();
+    BufReader::new(file).read_to_string(&mut contents).map_err(
+        |e| {
+            format!("{}: {}", path, e)
+        },
+    )?;
+    parse_string_table(&contents)
+}
+
+// switches every subsequent lookup over to the given locale; UI contexts
+// notice the change through generation() and re-layout themselves
+pub fn set_locale(locale: &str) -> Result<(), String> {
+    let strings = if locale == DEFAULT_LOCALE {
+        HashMap::<String, String>::new()
+    } else {
+        load_string_table(locale)?
+    };
+
+    let mut table = STRINGS.write().unwrap();
+    table.locale = String::from(locale);
+    table.strings = strings;
+    table.generation += 1;
+    Ok(())
+}
+
+// every locale with a string table in the locale directory, sorted by name
+pub fn available_locales() -> Vec<String> {
+    let mut locales = Vec::<String>::new();
+    if let Ok(entries) = fs::read_dir(LOCALE_DIR) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.extension().map_or(false, |e| e == "json") {
+                if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                    locales.push(String::from(name));
+                }
+            }
+        }
+    }
+
+    if !locales.iter().any(|l| l == DEFAULT_LOCALE) {
+        locales.push(String::from(DEFAULT_LOCALE));
+    }
+    locales.sort();
+    locales
+}
+
+pub fn get_locale() -> String {
+    STRINGS.read().unwrap().locale.clone()
+}
+
+// incremented every time the locale changes
+pub fn generation() -> usize {
+    STRINGS.read().unwrap().generation
+}
+
+// looks up a message in the current locale, falling back to the default
+// locale and finally to the key itself
+pub fn tr(key: &str) -> String {
+    if let Some(s) = STRINGS.read().unwrap().strings.get(key) {
+        return s.clone();
+    }
+
+    match DEFAULT_STRINGS.get(key) {
+        Some(s) => s.clone(),
+        None => String::from(key),
+    }
+}
+
+// looks up a message and replaces its {0}, {1}, ... placeholders with args
+pub fn tr_args(key: &str, args: &[String]) -> String {
+    format_message(&tr(key), args)
+}
+
+pub fn format_message(template: &str, args: &[String]) -> String {
+    let mut message = String::from(template);
+    for (i, arg) in args.iter().enumerate() {
+        message = message.replace(&format!("{{{}}}", i), arg);
+    }
+    message
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::io::Read;
+    use std::path::Path;
+
+    use std::collections::HashSet;
+
+    use utility::settings;
+    use utility::settings::DisplayMode;
+    use utility::ui::LayoutDef;
+    use super::{format_message, parse_string_table, tr, available_locales, DEFAULT_STRINGS,
+                DEFAULT_LOCALE, LOCALE_DIR};
+
+    // calls that take message keys, and which of their arguments are keys;
+    // where one call ends another, the first listed wins
+    const KEY_MARKERS: [(&'static str, &'static [usize]); 8] = [
+        ("Dialog::localized(", &[1, 2]),
+        ("Dialog::confirm(", &[1, 2]),
+        ("tr(", &[0]),
+        ("tr_args(", &[0]),
+        ("localized(", &[0]),
+        ("localized_toggle(", &[0]),
+        ("set_localized_tooltip(", &[0]),
+        ("add_localized_button(", &[0]),
+    ];
+
+    // the arguments of a call, given the source just after its opening
+    // parenthesis, split at the commas between them
+    fn call_arguments(source: &str) -> Vec<&str> {
+        let mut args = Vec::<&str>::new();
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut start = 0;
+        for (i, c) in source.char_indices() {
+            if in_string {
+                if c == '"' && !escaped {
+                    in_string = false;
+                }
+                escaped = c == '\\' && !escaped;
+                continue;
+            }
+
+            match c {
+                '"' => in_string = true,
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' if depth > 0 => depth -= 1,
+                ')' | ']' | '}' => {
+                    args.push(&source[start..i]);
+                    break;
+                }
+                ',' if depth == 0 => {
+                    args.push(&source[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        args
+    }
+
+    // string literal keys passed to the calls above; keys built at runtime
+    // are checked separately
+    fn source_keys(source: &str) -> Vec<String> {
+        let mut keys = Vec::<String>::new();
+        let mut seen = HashSet::<usize>::new();
+        for &(marker, indices) in KEY_MARKERS.iter() {
+            for (start, _) in source.match_indices(marker) {
+                //skips the likes of to_str( and markers quoted in strings
+                let before = source[..start].chars().next_back();
+                if before.map_or(false, |c| c.is_alphanumeric() || c == '_' || c == '"') {
+                    continue;
+                }
+                let open = start + marker.len();
+                if !seen.insert(open) {
+                    continue;
+                }
+
+                let args = call_arguments(&source[open..]);
+                for &i in indices.iter() {
+                    let arg = args.get(i).map_or("", |a| a.trim());
+                    if arg.len() >= 2 && arg.starts_with('"') && arg.ends_with('"') {
+                        keys.push(String::from(&arg[1..arg.len() - 1]));
+                    }
+                }
+            }
+        }
+        keys
+    }
+
+    fn collect_keys(dir: &Path, keys: &mut Vec<(String, String)>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                collect_keys(&path, keys);
+            } else if path.extension().map_or(false, |e| e == "rs") {
+                let mut source = String::new();
+                fs::File::open(&path)
+                    .unwrap()
+                    .read_to_string(&mut source)
+                    .unwrap();
+
+                for key in source_keys(&source) {
+                    keys.push((key, path.display().to_string()));
+                }
+            }
+        }
+    }
+
+    fn collect_layout_keys(dir: &Path, keys: &mut Vec<(String, String)>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let layout = LayoutDef::from_file(path.to_str().unwrap()).unwrap();
+            for key in layout.message_keys() {
+                keys.push((String::from(key), path.display().to_string()));
+            }
+        }
+    }
+
+    #[test]
+    fn every_referenced_key_exists_in_default_locale() {
+        let mut keys = Vec::<(String, String)>::new();
+        collect_keys(Path::new("src"), &mut keys);
+        collect_layout_keys(Path::new("resources/layouts"), &mut keys);
+        assert!(!keys.is_empty());
+
+        for &(ref key, ref file) in keys.iter() {
+            assert!(
+                DEFAULT_STRINGS.contains_key(key),
+                "Message key \"{}\" used in {} is missing from the default locale",
+                key,
+                file
+            );
+        }
+    }
+
+    #[test]
+    fn keys_are_found_across_lines_and_after_other_arguments() {
+        let source = "Dialog::confirm(\n    ID,\n    \"a.title\",\n    \"a.message\",\n);\n\
+                      tr_args(\"b\", &[tr(\"c\"), x.to_str(\"d\")]);\n\
+                      dialog.add_localized_button(\n\"e\", CHOICE_YES);";
+        let mut keys = source_keys(source);
+        keys.sort();
+        assert_eq!(keys, vec!["a.message", "a.title", "b", "c", "e"]);
+    }
+
+    #[test]
+    fn every_generated_key_exists_in_default_locale() {
+        let mut keys = Vec::<String>::new();
+        for mode in DisplayMode::all().iter() {
+            keys.push(format!("display.{}", mode.name()));
+        }
+        for action in settings::KEY_ACTIONS.iter() {
+            keys.push(format!("options.action_{}", action));
+        }
+        for locale in available_locales() {
+            keys.push(format!("language.{}", locale));
+        }
+
+        for key in keys.iter() {
+            assert!(
+                DEFAULT_STRINGS.contains_key(key),
+                "Message key \"{}\" is missing from the default locale",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn every_locale_translates_every_default_key() {
+        for entry in fs::read_dir(LOCALE_DIR).unwrap() {
+            let path = entry.unwrap().path();
+            let mut contents = String::new();
+            fs::File::open(&path)
+                .unwrap()
+                .read_to_string(&mut contents)
+                .unwrap();
+            let strings = parse_string_table(&contents).unwrap();
+
+            for key in DEFAULT_STRINGS.keys() {
+                assert!(
+                    strings.contains_key(key),
+                    "Message key \"{}\" is missing from {}",
+                    key,
+                    path.display()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn format_arguments_are_substituted() {
+        let message = format_message(
+            "({0}, {1}): {2} {0}",
+            &[String::from("1"), String::from("2"), String::from("x")],
+        );
+        assert_eq!(message, "(1, 2): x 1");
+    }
+
+    #[test]
+    fn available_locales_include_the_default() {
+        let locales = available_locales();
+        assert!(locales.contains(&String::from(DEFAULT_LOCALE)));
+        assert!(locales.contains(&String::from("es")));
+    }
+
+    #[test]
+    fn missing_keys_fall_back_to_the_key() {
+        let key = "missing.key";
+        assert_eq!(tr(key), key);
+    }
+}
@@ -1,2 +1,11 @@
+pub mod atlas;
+pub mod audio;
 pub mod debug;
+pub mod display;
+pub mod files;
+pub mod fonts;
+pub mod i18n;
+pub mod settings;
+pub mod theme;
 pub mod ui;
+pub mod viewport;
@@ -0,0 +1,320 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use ggez::event::Keycode;
+use serde_json;
+
+use utility::files;
+use utility::i18n;
+
+// stored in the user data directory ggez sets up for the game
+pub const SETTINGS_FILE_NAME: &'static str = "settings.json";
+
+pub const VOLUME_STEP: f32 = 0.1;
+
+// every action that can be bound to a key
+pub const KEY_ACTIONS: [&'static str; 3] = ["toggle_debug", "regenerate_map", "back"];
+
+lazy_static! {
+    static ref SETTINGS: RwLock<SettingsStore> = RwLock::new(SettingsStore {
+        settings: Settings::default(),
+        path: None,
+    });
+}
+
+struct SettingsStore {
+    settings: Settings,
+    path: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+pub enum DisplayMode {
+    Windowed,
+    Fullscreen,
+    Borderless,
+}
+
+impl DisplayMode {
+    pub fn all() -> [DisplayMode; 3] {
+        [
+            DisplayMode::Windowed,
+            DisplayMode::Fullscreen,
+            DisplayMode::Borderless,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            DisplayMode::Windowed => "windowed",
+            DisplayMode::Fullscreen => "fullscreen",
+            DisplayMode::Borderless => "borderless",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<DisplayMode> {
+        DisplayMode::all().iter().find(|m| m.name() == name).cloned()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub mode: DisplayMode,
+    pub width: u32,
+    pub height: u32,
+    pub vsync: bool,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> DisplaySettings {
+        DisplaySettings {
+            mode: DisplayMode::Windowed,
+            width: 1024,
+            height: 768,
+            vsync: false,
+        }
+    }
+}
+
+// volumes range from 0 to 1
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub effects_volume: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> AudioSettings {
+        AudioSettings {
+            master_volume: 1.0,
+            music_volume: 0.7,
+            effects_volume: 1.0,
+        }
+    }
+}
+
+// moves a volume by whole steps, keeping it between 0 and 1
+pub fn step_volume(volume: f32, steps: i32) -> f32 {
+    let stepped = ((volume / VOLUME_STEP).round() + steps as f32) * VOLUME_STEP;
+    stepped.max(0.0).min(1.0)
+}
+
+// actions map to SDL keycodes; an action missing from the file (say, one
+// added after it was written) gets its default key
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct KeyBindings {
+    bindings: BTreeMap<String, i32>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        let mut bindings = BTreeMap::<String, i32>::new();
+        bindings.insert(String::from("toggle_debug"), Keycode::F3 as i32);
+        bindings.insert(String::from("regenerate_map"), Keycode::R as i32);
+        bindings.insert(String::from("back"), Keycode::Backspace as i32);
+        KeyBindings { bindings: bindings }
+    }
+}
+
+impl KeyBindings {
+    pub fn get(&self, action: &str) -> Option<Keycode> {
+        match self.bindings.get(action) {
+            Some(code) => Keycode::from_i32(*code),
+            None => {
+                KeyBindings::default().bindings.get(action).and_then(
+                    |code| Keycode::from_i32(*code),
+                )
+            }
+        }
+    }
+
+    // binds the key to the action, taking it away from any other action
+    pub fn set(&mut self, action: &str, keycode: Keycode) {
+        for action in KEY_ACTIONS.iter() {
+            if self.get(action) == Some(keycode) {
+                self.bindings.insert(String::from(*action), 0);
+            }
+        }
+        self.bindings.insert(String::from(action), keycode as i32);
+    }
+
+    pub fn matches(&self, action: &str, keycode: Keycode) -> bool {
+        self.get(action) == Some(keycode)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(default)]
+pub struct GameplaySettings {
+    pub locale: String,
+    pub show_tooltips: bool,
+    pub confirm_actions: bool,
+}
+
+impl Default for GameplaySettings {
+    fn default() -> GameplaySettings {
+        GameplaySettings {
+            locale: String::from(i18n::DEFAULT_LOCALE),
+            show_tooltips: true,
+            confirm_actions: true,
+        }
+    }
+}
+
+// every section defaults on its own, so settings files written by older
+// versions of the game keep loading as new sections are added
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, Default)]
+pub struct Settings {
+    #[serde(default)]
+    pub display: DisplaySettings,
+    #[serde(default)]
+    pub audio: AudioSettings,
+    #[serde(default)]
+    pub keys: KeyBindings,
+    #[serde(default)]
+    pub gameplay: GameplaySettings,
+}
+
+impl Settings {
+    pub fn from_json(contents: &str) -> Result<Settings, String> {
+        serde_json::from_str(contents).map_err(|e| format!("Invalid settings: {}", e))
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| format!("Unable to encode settings: {}", e))
+    }
+
+    pub fn from_file(path: &Path) -> Result<Settings, String> {
+        let file = File::open(path).map_err(
+            |e| format!("{}: {}", path.display(), e),
+        )?;
+        let mut contents = String::new();
+        BufReader::new(file).read_to_string(&mut contents).map_err(
+            |e| {
+                format!("{}: {}", path.display(), e)
+            },
+        )?;
+        Settings::from_json(&contents)
+    }
+
+    pub fn to_file(&self, path: &Path) -> Result<(), String> {
+        let contents = self.to_json()?;
+        files::write_atomically(path, contents.as_bytes())
+    }
+}
+
+// reads the settings file from the given directory, falling back to the
+// defaults if it's missing or malformed; later calls to set() save there
+pub fn load(dir: &Path) {
+    let path = dir.join(SETTINGS_FILE_NAME);
+    let settings = if path.exists() {
+        match Settings::from_file(&path) {
+            Ok(settings) => settings,
+            Err(why) => {
+                println!("Unable to load settings, using defaults: {}", why);
+                Settings::default()
+            }
+        }
+    } else {
+        Settings::default()
+    };
+
+    let mut store = SETTINGS.write().unwrap();
+    store.settings = settings;
+    store.path = Some(path);
+}
+
+pub fn get() -> Settings {
+    SETTINGS.read().unwrap().settings.clone()
+}
+
+// reads part of the settings without copying all of them
+pub fn read<F, T>(f: F) -> T
+where
+    F: FnOnce(&Settings) -> T,
+{
+    f(&SETTINGS.read().unwrap().settings)
+}
+
+// changes the settings in place and saves them
+pub fn update<F>(f: F) -> Result<(), String>
+where
+    F: FnOnce(&mut Settings),
+{
+    let mut settings = get();
+    f(&mut settings);
+    set(settings)
+}
+
+// replaces the current settings and writes them out to the settings file
+pub fn set(settings: Settings) -> Result<(), String> {
+    let mut store = SETTINGS.write().unwrap();
+    store.settings = settings;
+    match store.path {
+        Some(ref path) => store.settings.to_file(path),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ggez::event::Keycode;
+    use super::{Settings, DisplayMode, KeyBindings, step_volume};
+
+    #[test]
+    fn settings_survive_a_round_trip() {
+        let mut settings = Settings::default();
+        settings.display.mode = DisplayMode::Borderless;
+        settings.display.width = 1920;
+        settings.display.height = 1080;
+
+        let json = settings.to_json().unwrap();
+        assert_eq!(Settings::from_json(&json).unwrap(), settings);
+    }
+
+    #[test]
+    fn missing_sections_use_defaults() {
+        assert_eq!(Settings::from_json("{}").unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn missing_fields_within_a_section_use_defaults() {
+        let settings = Settings::from_json(r#"{ "audio": { "music_volume": 0.2 } }"#).unwrap();
+        assert_eq!(settings.audio.music_volume, 0.2);
+        assert_eq!(settings.audio.master_volume, Settings::default().audio.master_volume);
+    }
+
+    #[test]
+    fn volume_steps_are_clamped() {
+        assert!((step_volume(0.5, 2) - 0.7).abs() < 0.0001);
+        assert_eq!(step_volume(0.9, 5), 1.0);
+        assert_eq!(step_volume(0.1, -3), 0.0);
+    }
+
+    #[test]
+    fn binding_a_key_unbinds_it_elsewhere() {
+        let mut keys = KeyBindings::default();
+        assert!(keys.matches("toggle_debug", Keycode::F3));
+
+        keys.set("back", Keycode::F3);
+        assert!(keys.matches("back", Keycode::F3));
+        assert_eq!(keys.get("toggle_debug"), None);
+
+        //actions missing from a settings file get their default key
+        let keys: KeyBindings = ::serde_json::from_str(r#"{ "bindings": {} }"#).unwrap();
+        assert!(keys.matches("regenerate_map", Keycode::R));
+    }
+
+    #[test]
+    fn display_modes_are_found_by_name() {
+        for mode in DisplayMode::all().iter() {
+            assert_eq!(DisplayMode::from_name(mode.name()), Some(*mode));
+        }
+        assert_eq!(DisplayMode::from_name("sideways"), None);
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+use ggez::graphics::Color;
+use serde_json;
+
+use utility::fonts::{DEFAULT_FONT_NAME, DEFAULT_FONT_SIZE};
+
+pub const THEME_FILE: &'static str = "resources/theme.json";
+
+pub type ThemeColor = (u8, u8, u8);
+
+// a partial style as it appears in a theme file; unset values fall through to
+// whatever is beneath it (element override -> widget style -> built-in defaults)
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, Default)]
+pub struct StyleDef {
+    #[serde(default)]
+    pub color: Option<ThemeColor>,
+    #[serde(default)]
+    pub color_hover: Option<ThemeColor>,
+    #[serde(default)]
+    pub color_border: Option<ThemeColor>,
+    #[serde(default)]
+    pub color_pressed: Option<ThemeColor>,
+    #[serde(default)]
+    pub color_checked: Option<ThemeColor>,
+    #[serde(default)]
+    pub color_disabled: Option<ThemeColor>,
+    #[serde(default)]
+    pub color_fill: Option<ThemeColor>,
+    #[serde(default)]
+    pub font_color: Option<ThemeColor>,
+    #[serde(default)]
+    pub font_color_disabled: Option<ThemeColor>,
+    #[serde(default)]
+    pub horiz_padding: Option<f32>,
+    #[serde(default)]
+    pub vert_padding: Option<f32>,
+    #[serde(default)]
+    pub border_width: Option<f32>,
+    #[serde(default)]
+    pub font: Option<String>,
+    #[serde(default)]
+    pub font_size: Option<u32>,
+    #[serde(default)]
+    pub image: Option<String>,
+    #[serde(default)]
+    pub image_hover: Option<String>,
+    #[serde(default)]
+    pub image_pressed: Option<String>,
+    #[serde(default)]
+    pub palette: HashMap<String, ThemeColor>,
+}
+
+// a fully-resolved style, handed to UI elements when they're drawn
+#[derive(Clone, PartialEq, Debug)]
+pub struct Style {
+    pub color: Color,
+    pub color_hover: Color,
+    pub color_border: Color,
+    pub color_pressed: Color,
+    pub color_checked: Color,
+    pub color_disabled: Color,
+    // the filled part of a progress bar
+    pub color_fill: Color,
+    pub font_color: Color,
+    pub font_color_disabled: Color,
+    pub horiz_padding: f32,
+    pub vert_padding: f32,
+    pub border_width: f32,
+    pub font: String,
+    pub font_size: u32,
+    // atlas regions drawn in place of the flat background, when set
+    pub image: Option<String>,
+    pub image_hover: Option<String>,
+    pub image_pressed: Option<String>,
+    pub palette: HashMap<String, Color>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Theme {
+    #[serde(default)]
+    widgets: HashMap<String, StyleDef>,
+    #[serde(default)]
+    elements: HashMap<String, StyleDef>,
+}
+
+impl Style {
+    pub fn default_for(widget_type: &str) -> Style {
+        let mut style = Style {
+            color: Color::from((29, 50, 76)),
+            color_hover: Color::from((47, 66, 91)),
+            color_border: Color::from((1, 4, 8)),
+            color_pressed: Color::from((20, 35, 54)),
+            color_checked: Color::from((72, 98, 128)),
+            color_disabled: Color::from((38, 42, 48)),
+            color_fill: Color::from((72, 98, 128)),
+            font_color: Color::from((178, 164, 141)),
+            font_color_disabled: Color::from((86, 86, 86)),
+            horiz_padding: 4.0,
+            vert_padding: 2.0,
+            border_width: 2.0,
+            font: String::from(DEFAULT_FONT_NAME),
+            font_size: DEFAULT_FONT_SIZE,
+            image: None,
+            image_hover: None,
+            image_pressed: None,
+            palette: HashMap::<String, Color>::new(),
+        };
+
+        match widget_type {
+            "button" => {
+                style.font_color = Color::from((1, 4, 8));
+            }
+            "tooltip" => {
+                style.color = Color::from((1, 4, 8));
+                style.color_border = Color::from((47, 66, 91));
+                style.border_width = 1.0;
+            }
+            "list" | "scroll_panel" | "progress_bar" => {
+                style.color = Color::from((1, 4, 8));
+                style.color_border = Color::from((29, 50, 76));
+                style.border_width = 1.0;
+            }
+            "dialog" => {
+                style.color = Color::from((1, 4, 8));
+                style.color_border = Color::from((47, 66, 91));
+            }
+            "map" => {
+                style.color_border = Color::from((255, 0, 0));
+                style.border_width = 3.0;
+                style.palette.insert(
+                    String::from("water"),
+                    Color::from((72, 104, 150)),
+                );
+            }
+            _ => {}
+        }
+
+        style
+    }
+
+    pub fn get_palette_color(&self, name: &str) -> Color {
+        match self.palette.get(name) {
+            Some(color) => *color,
+            None => self.color,
+        }
+    }
+
+    pub fn apply(&mut self, def: &StyleDef) {
+        if let Some(c) = def.color {
+            self.color = Color::from(c);
+        }
+        if let Some(c) = def.color_hover {
+            self.color_hover = Color::from(c);
+        }
+        if let Some(c) = def.color_border {
+            self.color_border = Color::from(c);
+        }
+        if let Some(c) = def.color_pressed {
+            self.color_pressed = Color::from(c);
+        }
+        if let Some(c) = def.color_checked {
+            self.color_checked = Color::from(c);
+        }
+        if let Some(c) = def.color_disabled {
+            self.color_disabled = Color::from(c);
+        }
+        if let Some(c) = def.color_fill {
+            self.color_fill = Color::from(c);
+        }
+        if let Some(c) = def.font_color {
+            self.font_color = Color::from(c);
+        }
+        if let Some(c) = def.font_color_disabled {
+            self.font_color_disabled = Color::from(c);
+        }
+        if let Some(p) = def.horiz_padding {
+            self.horiz_padding = p;
+        }
+        if let Some(p) = def.vert_padding {
+            self.vert_padding = p;
+        }
+        if let Some(w) = def.border_width {
+            self.border_width = w;
+        }
+        if let Some(ref f) = def.font {
+            self.font = f.clone();
+        }
+        if let Some(s) = def.font_size {
+            self.font_size = s;
+        }
+        if let Some(ref i) = def.image {
+            self.image = Some(i.clone());
+        }
+        if let Some(ref i) = def.image_hover {
+            self.image_hover = Some(i.clone());
+        }
+        if let Some(ref i) = def.image_pressed {
+            self.image_pressed = Some(i.clone());
+        }
+        for (name, c) in def.palette.iter() {
+            self.palette.insert(name.clone(), Color::from(*c));
+        }
+    }
+}
+
+impl Theme {
+    pub fn new() -> Theme {
+        Theme {
+            widgets: HashMap::<String, StyleDef>::new(),
+            elements: HashMap::<String, StyleDef>::new(),
+        }
+    }
+
+    // falls back to the built-in styles if the theme file is missing or malformed
+    pub fn load() -> Theme {
+        match Theme::from_file(THEME_FILE) {
+            Ok(theme) => theme,
+            Err(why) => {
+                println!("Unable to load theme, using defaults: {}", why);
+                Theme::new()
+            }
+        }
+    }
+
+    pub fn from_file(path: &str) -> Result<Theme, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut contents = String::new();
+        BufReader::new(file).read_to_string(&mut contents).map_err(
+            |e| {
+                format!("{}: {}", path, e)
+            },
+        )?;
+        Theme::from_json(&contents)
+    }
+
+    pub fn from_json(contents: &str) -> Result<Theme, String> {
+        serde_json::from_str(contents).map_err(|e| format!("Invalid theme: {}", e))
+    }
+
+    pub fn get_style(&self, widget_type: &str, element_name: &str) -> Style {
+        let mut style = Style::default_for(widget_type);
+
+        if let Some(def) = self.widgets.get(widget_type) {
+            style.apply(def);
+        }
+
+        if let Some(def) = self.elements.get(element_name) {
+            style.apply(def);
+        }
+
+        style
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Theme, Style};
+    use ggez::graphics::Color;
+
+    #[test]
+    fn element_overrides_take_precedence_over_widget_styles() {
+        let theme = Theme::from_json(
+            r#"{
+                "widgets": {
+                    "button": { "color": [10, 20, 30], "horiz_padding": 8.0 }
+                },
+                "elements": {
+                    "btn_quit": { "color": [200, 0, 0] }
+                }
+            }"#,
+        ).unwrap();
+
+        let play = theme.get_style("button", "btn_play");
+        assert!(play.color == Color::from((10, 20, 30)));
+        assert!(play.horiz_padding == 8.0);
+
+        let quit = theme.get_style("button", "btn_quit");
+        assert!(quit.color == Color::from((200, 0, 0)));
+        assert!(quit.horiz_padding == 8.0);
+
+        //values not mentioned anywhere in the theme come from the defaults
+        assert!(quit.color_border == Style::default_for("button").color_border);
+    }
+
+    #[test]
+    fn checked_color_does_not_recolor_progress_bars() {
+        let theme = Theme::from_json(
+            r#"{
+                "widgets": {
+                    "progress_bar": { "color_checked": [255, 0, 0] }
+                }
+            }"#,
+        ).unwrap();
+
+        let bar = theme.get_style("progress_bar", "pb_loading");
+        assert!(bar.color_fill == Style::default_for("progress_bar").color_fill);
+    }
+
+    #[test]
+    fn missing_theme_file_is_an_error() {
+        assert!(Theme::from_file("does/not/exist.json").is_err());
+    }
+}
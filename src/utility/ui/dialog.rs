@@ -0,0 +1,209 @@
+use ggez::graphics;
+use ggez::graphics::{Color, Rect};
+use ggez::Context;
+
+use substate::states::{StateInfo, StoredValue};
+use utility::atlas;
+use utility::fonts;
+use utility::theme::Style;
+use super::{UIElement, Button, RichLabel, Alignment};
+
+pub const CHOICE_YES: &'static str = "yes";
+pub const CHOICE_NO: &'static str = "no";
+
+const DIALOG_WIDTH: f32 = 360.0;
+const DIALOG_PADDING: f32 = 16.0;
+const DIALOG_SPACING: f32 = 12.0;
+const BUTTON_SPACING: f32 = 8.0;
+const TITLE_FONT_SIZE: u32 = 20;
+const BACKDROP_ALPHA: u8 = 160;
+
+// dialog buttons report back through the dialog rather than their own callback
+fn no_action(_: &mut StateInfo) {}
+
+// a modal box shown through UIContext::show_dialog; while it's open the rest
+// of the context gets no input. the chosen button's choice is written to the
+// owning state's StateInfo under the dialog's id as a Textual value
+pub struct Dialog {
+    id: &'static str,
+    title: RichLabel,
+    message: RichLabel,
+    buttons: Vec<(Button, &'static str)>,
+    button_style: Style,
+    closed: bool,
+}
+
+impl Dialog {
+    pub fn new(id: &'static str, title: &str, message: &str) -> Dialog {
+        Dialog::from_labels(id, RichLabel::new(title), RichLabel::new(message))
+    }
+
+    pub fn localized(id: &'static str, title_key: &'static str, message_key: &'static str) -> Dialog {
+        Dialog::from_labels(
+            id,
+            RichLabel::localized(title_key),
+            RichLabel::localized(message_key),
+        )
+    }
+
+    // a localized dialog with yes/no buttons, answering CHOICE_YES or CHOICE_NO
+    pub fn confirm(id: &'static str, title_key: &'static str, message_key: &'static str) -> Dialog {
+        let mut dialog = Dialog::localized(id, title_key, message_key);
+        dialog.add_localized_button("dialog.yes", CHOICE_YES);
+        dialog.add_localized_button("dialog.no", CHOICE_NO);
+        dialog
+    }
+
+    fn from_labels(id: &'static str, mut title: RichLabel, mut message: RichLabel) -> Dialog {
+        let inner_width = DIALOG_WIDTH - 2.0 * DIALOG_PADDING;
+
+        title.set_font(fonts::DEFAULT_FONT_NAME, TITLE_FONT_SIZE);
+        title.set_max_width(inner_width);
+        title.set_alignment(Alignment::Center);
+        message.set_max_width(inner_width);
+        message.set_alignment(Alignment::Center);
+
+        Dialog {
+            id: id,
+            title: title,
+            message: message,
+            buttons: Vec::<(Button, &'static str)>::new(),
+            button_style: Style::default_for("button"),
+            closed: false,
+        }
+    }
+
+    pub fn add_button(&mut self, text: String, choice: &'static str) {
+        self.buttons.push((Button::new(text, no_action), choice));
+    }
+
+    pub fn add_localized_button(&mut self, key: &'static str, choice: &'static str) {
+        self.buttons.push((Button::localized(key, no_action), choice));
+    }
+
+    pub fn set_button_style(&mut self, style: Style) {
+        self.button_style = style;
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+impl UIElement for Dialog {
+    fn draw(&mut self, ctx: &mut Context, style: &Style) {
+        let screen = graphics::get_screen_coordinates(ctx);
+        let screen_w = screen.w;
+        let screen_h = screen.h * -1.0;
+
+        //dim everything underneath the dialog
+        graphics::set_color(ctx, Color::from((0, 0, 0, BACKDROP_ALPHA))).unwrap();
+        graphics::rectangle(
+            ctx,
+            graphics::DrawMode::Fill,
+            Rect::new(screen_w / 2.0, screen_h / 2.0, screen_w, screen_h),
+        ).unwrap();
+
+        self.title.prepare(ctx, style);
+        self.message.prepare(ctx, style);
+
+        let mut buttons_width = 0.0;
+        let mut buttons_height: f32 = 0.0;
+        for &mut (ref mut btn, _) in self.buttons.iter_mut() {
+            btn.prepare(ctx, &self.button_style);
+            buttons_width += btn.get_width();
+            buttons_height = buttons_height.max(btn.get_height());
+        }
+        if !self.buttons.is_empty() {
+            buttons_width += BUTTON_SPACING * (self.buttons.len() - 1) as f32;
+        }
+
+        let height = 2.0 * DIALOG_PADDING + self.title.get_height() + DIALOG_SPACING +
+            self.message.get_height() + DIALOG_SPACING + buttons_height;
+        let top = (screen_h - height) / 2.0;
+
+        let rect = Rect::new(screen_w / 2.0, screen_h / 2.0, DIALOG_WIDTH, height);
+        let drawn = match style.image {
+            Some(ref image) => atlas::draw(ctx, image, &rect, graphics::WHITE),
+            None => false,
+        };
+        if !drawn {
+            graphics::set_color(ctx, style.color).unwrap();
+            graphics::rectangle(ctx, graphics::DrawMode::Fill, rect).unwrap();
+            graphics::set_line_width(ctx, style.border_width);
+            graphics::set_color(ctx, style.color_border).unwrap();
+            graphics::rectangle(ctx, graphics::DrawMode::Line, rect).unwrap();
+        }
+
+        let mut y = top + DIALOG_PADDING;
+        let title_height = self.title.get_height();
+        self.title.set_position(screen_w / 2.0, y + title_height / 2.0);
+        self.title.draw(ctx, style);
+
+        y += title_height + DIALOG_SPACING;
+        let message_height = self.message.get_height();
+        self.message.set_position(screen_w / 2.0, y + message_height / 2.0);
+        self.message.draw(ctx, style);
+
+        y += message_height + DIALOG_SPACING;
+        let mut x = (screen_w - buttons_width) / 2.0;
+        for &mut (ref mut btn, _) in self.buttons.iter_mut() {
+            let width = btn.get_width();
+            btn.set_position(x + width / 2.0, y + buttons_height / 2.0);
+            btn.draw(ctx, &self.button_style);
+            x += width + BUTTON_SPACING;
+        }
+    }
+
+    fn widget_type(&self) -> &'static str {
+        "dialog"
+    }
+
+    fn update(&mut self, dt: f32) {
+        for &mut (ref mut btn, _) in self.buttons.iter_mut() {
+            btn.update(dt);
+        }
+    }
+
+    fn hover(&mut self, mouse_x: i32, mouse_y: i32) {
+        for &mut (ref mut btn, _) in self.buttons.iter_mut() {
+            btn.hover(mouse_x, mouse_y);
+        }
+    }
+
+    fn click(&mut self, mouse_x: i32, mouse_y: i32, info: &mut StateInfo) {
+        for &mut (ref mut btn, _) in self.buttons.iter_mut() {
+            btn.click(mouse_x, mouse_y, info);
+        }
+    }
+
+    // like any other button, a choice is only made once the mouse is released
+    // over the button it was pressed on
+    fn release(&mut self, mouse_x: i32, mouse_y: i32, info: &mut StateInfo) {
+        let choice = self.buttons
+            .iter()
+            .find(|&&(ref btn, _)| btn.is_pressed() && btn.contains(mouse_x, mouse_y))
+            .map(|&(_, choice)| choice);
+
+        for &mut (ref mut btn, _) in self.buttons.iter_mut() {
+            btn.release(mouse_x, mouse_y, info);
+        }
+
+        if let Some(choice) = choice {
+            info.set_value(
+                self.id,
+                StoredValue::Textual { value: String::from(choice) },
+            );
+            info.refresh_ui();
+            self.closed = true;
+        }
+    }
+
+    fn locale_changed(&mut self) {
+        self.title.locale_changed();
+        self.message.locale_changed();
+        for &mut (ref mut btn, _) in self.buttons.iter_mut() {
+            btn.locale_changed();
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use ggez::graphics;
+use ggez::graphics::{Color, Rect};
+use ggez::Context;
+
+use utility::atlas;
+use utility::theme::Style;
+use super::UIElement;
+
+// a region of the texture atlas, drawn at its own size unless told otherwise
+pub struct Image {
+    x: f32,
+    y: f32,
+    size: Option<(f32, f32)>,
+    region: String,
+    color: Color,
+}
+
+impl Image {
+    pub fn new(region: &str) -> Image {
+        Image {
+            x: 0.0,
+            y: 0.0,
+            size: None,
+            region: String::from(region),
+            color: graphics::WHITE,
+        }
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    pub fn set_size(&mut self, w: f32, h: f32) {
+        self.size = Some((w, h));
+    }
+
+    pub fn set_region(&mut self, region: &str) {
+        self.region = String::from(region);
+    }
+
+    // multiplied with the image's own colors
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.get_size().0
+    }
+
+    pub fn get_height(&self) -> f32 {
+        self.get_size().1
+    }
+
+    fn get_size(&self) -> (f32, f32) {
+        match self.size {
+            Some(size) => size,
+            None => {
+                atlas::get_region(&self.region).map_or((0.0, 0.0), |r| (r.w as f32, r.h as f32))
+            }
+        }
+    }
+}
+
+impl UIElement for Image {
+    fn draw(&mut self, ctx: &mut Context, style: &Style) {
+        let (w, h) = self.get_size();
+        atlas::draw(ctx, &self.region, &Rect::new(self.x, self.y, w, h), self.color);
+    }
+
+    fn widget_type(&self) -> &'static str {
+        "image"
+    }
+}
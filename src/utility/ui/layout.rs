@@ -0,0 +1,329 @@
+use std::collections::HashSet;
+use std::fs;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde_json;
+
+use substate::states::StateInfo;
+use utility::fonts;
+use utility::theme::StyleDef;
+use super::{UIContext, Label, Button, RichLabel, Alignment, Image, ScrollPanel};
+
+// how often a watched layout file is checked for changes
+const WATCH_INTERVAL_MS: u64 = 500;
+
+lazy_static! {
+    static ref NAMES: Mutex<HashSet<&'static str>> = Mutex::new(HashSet::<&'static str>::new());
+}
+
+// element names and message keys live for as long as the game does, the same
+// as the literals the sub-states use; each distinct string is only leaked once,
+// however many times a layout is reloaded
+pub fn intern(s: &str) -> &'static str {
+    let mut names = NAMES.lock().unwrap();
+    if let Some(name) = names.get(s) {
+        return name;
+    }
+
+    let name: &'static str = Box::leak(String::from(s).into_boxed_str());
+    names.insert(name);
+    name
+}
+
+// layout buttons report what they do through StateInfo::trigger instead
+fn no_action(_: &mut StateInfo) {}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ElementType {
+    Label,
+    RichLabel,
+    Button,
+    Toggle,
+    Image,
+    ScrollPanel,
+}
+
+// one element of a layout; text comes from a message key, or is given as is
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct ElementDef {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub element_type: ElementType,
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub tooltip: Option<String>,
+    #[serde(default)]
+    pub action: Option<String>,
+    #[serde(default)]
+    pub font: Option<String>,
+    #[serde(default)]
+    pub font_size: Option<u32>,
+    #[serde(default)]
+    pub width: Option<f32>,
+    #[serde(default)]
+    pub height: Option<f32>,
+    #[serde(default)]
+    pub max_width: Option<f32>,
+    #[serde(default)]
+    pub centered: bool,
+    #[serde(default)]
+    pub region: Option<String>,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    #[serde(default)]
+    pub checked: Option<bool>,
+    #[serde(default)]
+    pub style: Option<StyleDef>,
+}
+
+// the elements of a UI, without their positions; sub-states still lay the
+// elements out themselves, since that depends on the screen size
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct LayoutDef {
+    pub elements: Vec<ElementDef>,
+}
+
+impl LayoutDef {
+    pub fn from_json(contents: &str) -> Result<LayoutDef, String> {
+        let layout: LayoutDef = serde_json::from_str(contents).map_err(
+            |e| format!("Invalid layout: {}", e),
+        )?;
+        layout.validate()?;
+        Ok(layout)
+    }
+
+    pub fn from_file(path: &str) -> Result<LayoutDef, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut contents = String::new();
+        BufReader::new(file).read_to_string(&mut contents).map_err(
+            |e| {
+                format!("{}: {}", path, e)
+            },
+        )?;
+        LayoutDef::from_json(&contents).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let mut names = HashSet::<&str>::new();
+        for def in self.elements.iter() {
+            if !names.insert(&def.name) {
+                return Err(format!("Element \"{}\" is defined twice", def.name));
+            }
+
+            let needs_text = match def.element_type {
+                ElementType::Image => false,
+                _ => true,
+            };
+            if needs_text && def.key.is_none() && def.text.is_none() {
+                return Err(format!("Element \"{}\" has no key or text", def.name));
+            }
+            if def.element_type == ElementType::Image && def.region.is_none() {
+                return Err(format!("Image \"{}\" has no region", def.name));
+            }
+        }
+        Ok(())
+    }
+
+    // adds every element to the context, returning their names
+    pub fn build(&self, ui_context: &mut UIContext) -> Vec<&'static str> {
+        let mut names = Vec::<&'static str>::new();
+        for def in self.elements.iter() {
+            let name = intern(&def.name);
+            build_element(def, name, ui_context);
+            if let Some(ref style) = def.style {
+                ui_context.set_style_override(name, style.clone());
+            }
+            names.push(name);
+        }
+        names
+    }
+
+    // every message key the layout refers to
+    pub fn message_keys(&self) -> Vec<&str> {
+        let mut keys = Vec::<&str>::new();
+        for def in self.elements.iter() {
+            keys.extend(def.key.iter().map(|k| k.as_str()));
+            keys.extend(def.tooltip.iter().map(|k| k.as_str()));
+        }
+        keys
+    }
+}
+
+fn build_element(def: &ElementDef, name: &'static str, ui_context: &mut UIContext) {
+    let font = def.font.as_ref().map_or(fonts::DEFAULT_FONT_NAME, |f| f.as_str());
+
+    match def.element_type {
+        ElementType::Label => {
+            let mut label = match def.key {
+                Some(ref key) => Label::localized(intern(key)),
+                None => Label::new(def.text.clone().unwrap_or_default()),
+            };
+            if let Some(size) = def.font_size {
+                label.set_font(font, size);
+            }
+            ui_context.add_element(name, Box::new(label));
+        }
+        ElementType::RichLabel | ElementType::ScrollPanel => {
+            let mut label = match def.key {
+                Some(ref key) => RichLabel::localized(intern(key)),
+                None => RichLabel::new(&def.text.clone().unwrap_or_default()),
+            };
+            if let Some(size) = def.font_size {
+                label.set_font(font, size);
+            }
+            if def.centered {
+                label.set_alignment(Alignment::Center);
+            }
+
+            if def.element_type == ElementType::RichLabel {
+                if let Some(max_width) = def.max_width.or(def.width) {
+                    label.set_max_width(max_width);
+                }
+                ui_context.add_element(name, Box::new(label));
+            } else {
+                let mut panel = ScrollPanel::new(label);
+                panel.set_size(def.width.unwrap_or(0.0), def.height.unwrap_or(0.0));
+                ui_context.add_element(name, Box::new(panel));
+            }
+        }
+        ElementType::Button | ElementType::Toggle => {
+            let mut button = match (def.element_type, &def.key) {
+                (ElementType::Toggle, &Some(ref key)) => {
+                    Button::localized_toggle(intern(key), no_action)
+                }
+                (ElementType::Toggle, &None) => {
+                    Button::toggle(def.text.clone().unwrap_or_default(), no_action)
+                }
+                (_, &Some(ref key)) => Button::localized(intern(key), no_action),
+                (_, &None) => Button::new(def.text.clone().unwrap_or_default(), no_action),
+            };
+            if let Some(size) = def.font_size {
+                button.set_font(font, size);
+            }
+            if let Some(width) = def.width {
+                button.set_width(width);
+            }
+            if let Some(ref tooltip) = def.tooltip {
+                button.set_localized_tooltip(intern(tooltip));
+            }
+            if let Some(ref action) = def.action {
+                button.set_action(action.clone());
+            }
+            if let Some(enabled) = def.enabled {
+                button.set_enabled(enabled);
+            }
+            if let Some(checked) = def.checked {
+                button.set_checked(checked);
+            }
+            ui_context.add_element(name, Box::new(button));
+        }
+        ElementType::Image => {
+            let mut image = Image::new(def.region.as_ref().map_or("", |r| r.as_str()));
+            if let (Some(w), Some(h)) = (def.width, def.height) {
+                image.set_size(w, h);
+            }
+            ui_context.add_element(name, Box::new(image));
+        }
+    }
+}
+
+// a layout loaded from a file, along with what's needed to notice the file
+// changing underneath it
+pub struct LayoutWatch {
+    path: String,
+    names: Vec<&'static str>,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl LayoutWatch {
+    pub fn new(path: &str, names: Vec<&'static str>) -> LayoutWatch {
+        LayoutWatch {
+            path: String::from(path),
+            names: names,
+            modified: modified_time(path),
+            last_check: Instant::now(),
+        }
+    }
+
+    pub fn get_names(&self) -> &Vec<&'static str> {
+        &self.names
+    }
+
+    pub fn set_names(&mut self, names: Vec<&'static str>) {
+        self.names = names;
+    }
+
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    // true, at most every WATCH_INTERVAL_MS, if the file has been saved since
+    // it was last loaded
+    pub fn has_changed(&mut self) -> bool {
+        if self.last_check.elapsed() < Duration::from_millis(WATCH_INTERVAL_MS) {
+            return false;
+        }
+        self.last_check = Instant::now();
+
+        let modified = modified_time(&self.path);
+        if modified != self.modified {
+            self.modified = modified;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{intern, LayoutDef, ElementType};
+
+    #[test]
+    fn layouts_are_parsed_and_validated() {
+        let layout = LayoutDef::from_json(
+            r#"{
+                "elements": [
+                    { "name": "lbl_title", "type": "label", "key": "menu.title", "font_size": 32 },
+                    { "name": "btn_play", "type": "button", "key": "menu.play",
+                      "tooltip": "menu.play_tooltip", "action": "play" },
+                    { "name": "img_emblem", "type": "image", "region": "emblem" }
+                ]
+            }"#,
+        ).unwrap();
+        assert_eq!(layout.elements[1].element_type, ElementType::Button);
+        assert_eq!(layout.elements[1].action, Some(String::from("play")));
+        assert_eq!(
+            layout.message_keys(),
+            vec!["menu.title", "menu.play", "menu.play_tooltip"]
+        );
+
+        let duplicate = r#"{ "elements": [
+            { "name": "a", "type": "label", "text": "x" },
+            { "name": "a", "type": "label", "text": "y" }
+        ] }"#;
+        assert!(LayoutDef::from_json(duplicate).is_err());
+
+        let no_text = r#"{ "elements": [ { "name": "a", "type": "button" } ] }"#;
+        assert!(LayoutDef::from_json(no_text).is_err());
+    }
+
+    #[test]
+    fn interned_names_are_shared() {
+        let a = intern(&String::from("btn_interned"));
+        let b = intern(&String::from("btn_interned"));
+        assert_eq!(a.as_ptr(), b.as_ptr());
+    }
+}
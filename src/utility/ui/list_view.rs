@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+
+use ggez::graphics;
+use ggez::graphics::{Text, Rect, Point};
+use ggez::event::Keycode;
+use ggez::Context;
+
+use substate::states::StateInfo;
+use utility::fonts::FontSelection;
+use utility::theme::Style;
+use super::UIElement;
+use super::scroll::{ScrollState, SCROLLBAR_WIDTH, draw_text_clipped, draw_scrollbar};
+
+// the rows [first, last) that are at least partly inside a viewport scrolled
+// down by offset
+pub fn visible_rows(offset: f32, viewport: f32, row_height: f32, count: usize) -> (usize, usize) {
+    if row_height <= 0.0 || count == 0 {
+        return (0, 0);
+    }
+
+    let first = (offset / row_height).floor().max(0.0) as usize;
+    let last = ((offset + viewport) / row_height).ceil().max(0.0) as usize;
+    (first.min(count), last.min(count))
+}
+
+// intersects two center-based rects
+fn clip_rect(rect: &Rect, clip: &Rect) -> Option<Rect> {
+    let left = (rect.x - rect.w / 2.0).max(clip.x - clip.w / 2.0);
+    let right = (rect.x + rect.w / 2.0).min(clip.x + clip.w / 2.0);
+    let top = (rect.y - rect.h / 2.0).max(clip.y - clip.h / 2.0);
+    let bottom = (rect.y + rect.h / 2.0).min(clip.y + clip.h / 2.0);
+
+    if right > left && bottom > top {
+        Some(Rect::new(
+            (left + right) / 2.0,
+            (top + bottom) / 2.0,
+            right - left,
+            bottom - top,
+        ))
+    } else {
+        None
+    }
+}
+
+// a scrolling list of text rows; only the rows currently on screen have their
+// text rendered, so it stays cheap with thousands of items
+pub struct ListView {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    items: Vec<String>,
+    rendered: HashMap<usize, Text>,
+    font: FontSelection,
+    row_height: f32,
+    scroll: ScrollState,
+    selected: Option<usize>,
+    callback: Option<fn(info: &mut StateInfo, index: usize)>,
+    dragging: bool,
+    focused: bool,
+}
+
+impl ListView {
+    pub fn new(items: Vec<String>) -> ListView {
+        ListView {
+            x: 0.0,
+            y: 0.0,
+            width: 0.0,
+            height: 0.0,
+            items: items,
+            rendered: HashMap::<usize, Text>::new(),
+            font: FontSelection::new(),
+            row_height: 0.0,
+            scroll: ScrollState::new(),
+            selected: None,
+            callback: None,
+            dragging: false,
+            focused: false,
+        }
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    pub fn set_size(&mut self, w: f32, h: f32) {
+        self.width = w;
+        self.height = h;
+    }
+
+    pub fn set_font(&mut self, name: &str, size: u32) {
+        self.font.set(name, size);
+        self.rendered.clear();
+    }
+
+    pub fn set_items(&mut self, items: Vec<String>) {
+        self.items = items;
+        self.rendered.clear();
+        self.selected = None;
+        self.scroll.set_offset(0.0);
+    }
+
+    pub fn get_items(&self) -> &Vec<String> {
+        &self.items
+    }
+
+    // called with the item's index whenever the selection changes
+    pub fn set_callback(&mut self, callback: fn(info: &mut StateInfo, index: usize)) {
+        self.callback = Some(callback);
+    }
+
+    pub fn get_selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    // selects an item without calling the callback
+    pub fn set_selected(&mut self, index: Option<usize>) {
+        self.selected = index.and_then(|i| if i < self.items.len() { Some(i) } else { None });
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> f32 {
+        self.height
+    }
+
+    fn contains(&self, mouse_x: i32, mouse_y: i32) -> bool {
+        let mx = mouse_x as f32;
+        let my = mouse_y as f32;
+        mx >= self.x - (self.width / 2.0) && mx <= self.x + (self.width / 2.0) &&
+            my >= self.y - (self.height / 2.0) && my <= self.y + (self.height / 2.0)
+    }
+
+    fn row_at(&self, mouse_y: i32) -> Option<usize> {
+        if self.row_height <= 0.0 {
+            return None;
+        }
+
+        let top = self.y - self.height / 2.0;
+        let row = ((mouse_y as f32 - top + self.scroll.get_offset()) / self.row_height).floor();
+        if row >= 0.0 && (row as usize) < self.items.len() {
+            Some(row as usize)
+        } else {
+            None
+        }
+    }
+
+    fn select(&mut self, index: usize, info: &mut StateInfo) {
+        if self.selected == Some(index) {
+            return;
+        }
+
+        self.selected = Some(index);
+        let start = index as f32 * self.row_height;
+        self.scroll.scroll_into_view(start, start + self.row_height);
+        if let Some(callback) = self.callback {
+            callback(info, index);
+        }
+    }
+}
+
+impl UIElement for ListView {
+    fn draw(&mut self, ctx: &mut Context, style: &Style) {
+        if self.font.update(style) {
+            self.rendered.clear();
+        }
+        let font = self.font.get();
+        self.row_height = font.get_height() as f32 + 2.0 * style.vert_padding;
+
+        let rect = Rect::new(self.x, self.y, self.width, self.height);
+        self.scroll.set_sizes(self.items.len() as f32 * self.row_height, self.height);
+
+        graphics::set_color(ctx, style.color).unwrap();
+        graphics::rectangle(ctx, graphics::DrawMode::Fill, rect).unwrap();
+
+        let (first, last) = visible_rows(
+            self.scroll.get_offset(),
+            self.height,
+            self.row_height,
+            self.items.len(),
+        );
+
+        // forget the text of rows that have scrolled out of view
+        self.rendered.retain(|index, _| *index >= first && *index < last);
+
+        let top = self.y - self.height / 2.0 - self.scroll.get_offset();
+        let row_width = self.width - SCROLLBAR_WIDTH;
+        for index in first..last {
+            let row_y = top + (index as f32 + 0.5) * self.row_height;
+
+            if self.selected == Some(index) {
+                let row = Rect::new(
+                    self.x - SCROLLBAR_WIDTH / 2.0,
+                    row_y,
+                    row_width,
+                    self.row_height,
+                );
+                if let Some(visible) = clip_rect(&row, &rect) {
+                    graphics::set_color(ctx, style.color_hover).unwrap();
+                    graphics::rectangle(ctx, graphics::DrawMode::Fill, visible).unwrap();
+                }
+            }
+
+            if !self.rendered.contains_key(&index) {
+                let mut text = Text::new(ctx, &self.items[index], &font).unwrap();
+                text.set_filter(graphics::FilterMode::Nearest);
+                self.rendered.insert(index, text);
+            }
+
+            let text = &self.rendered[&index];
+            let text_x = self.x - self.width / 2.0 + style.horiz_padding + text.width() as f32 / 2.0;
+            graphics::set_color(ctx, style.font_color).unwrap();
+            draw_text_clipped(ctx, text, Point::new(text_x, row_y), &rect);
+        }
+
+        draw_scrollbar(ctx, &self.scroll, &rect, style);
+
+        graphics::set_line_width(ctx, style.border_width);
+        graphics::set_color(ctx, style.color_border).unwrap();
+        graphics::rectangle(ctx, graphics::DrawMode::Line, rect).unwrap();
+    }
+
+    fn widget_type(&self) -> &'static str {
+        "list"
+    }
+
+    fn click(&mut self, mouse_x: i32, mouse_y: i32, info: &mut StateInfo) {
+        self.focused = self.contains(mouse_x, mouse_y);
+        self.dragging = self.focused;
+
+        if self.focused {
+            if let Some(index) = self.row_at(mouse_y) {
+                self.select(index, info);
+            }
+        }
+    }
+
+    fn drag(&mut self, _mouse_x: i32, _mouse_y: i32, _xrel: i32, yrel: i32) {
+        if self.dragging {
+            self.scroll.scroll_by(-yrel as f32);
+        }
+    }
+
+    fn release(&mut self, _mouse_x: i32, _mouse_y: i32, _info: &mut StateInfo) {
+        self.dragging = false;
+    }
+
+    fn cancel_press(&mut self) {
+        self.dragging = false;
+    }
+
+    fn wheel(&mut self, mouse_x: i32, mouse_y: i32, amount: i32) {
+        if self.contains(mouse_x, mouse_y) {
+            self.scroll.wheel(amount);
+        }
+    }
+
+    // up and down move the selection, everything else just scrolls
+    fn key_down(&mut self, keycode: Keycode, info: &mut StateInfo) {
+        if !self.focused || self.items.is_empty() {
+            return;
+        }
+
+        let last = self.items.len() - 1;
+        match keycode {
+            Keycode::Up => {
+                let index = match self.selected {
+                    Some(i) if i > 0 => i - 1,
+                    _ => 0,
+                };
+                self.select(index, info);
+            }
+            Keycode::Down => {
+                let index = match self.selected {
+                    Some(i) if i < last => i + 1,
+                    Some(i) => i,
+                    None => 0,
+                };
+                self.select(index, info);
+            }
+            _ => {
+                self.scroll.key_down(keycode);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::visible_rows;
+
+    #[test]
+    fn only_rows_inside_the_viewport_are_visible() {
+        assert_eq!(visible_rows(0.0, 100.0, 20.0, 1000), (0, 5));
+
+        //a partly-scrolled row at either edge still counts
+        assert_eq!(visible_rows(30.0, 100.0, 20.0, 1000), (1, 7));
+
+        assert_eq!(visible_rows(19_950.0, 100.0, 20.0, 1000), (997, 1000));
+        assert_eq!(visible_rows(0.0, 100.0, 20.0, 3), (0, 3));
+        assert_eq!(visible_rows(0.0, 100.0, 20.0, 0), (0, 0));
+    }
+}
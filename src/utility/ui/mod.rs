@@ -0,0 +1,1098 @@
+mod dialog;
+mod image;
+mod layout;
+mod list_view;
+mod progress_bar;
+mod rich_label;
+mod scroll;
+mod tween;
+
+use std;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use ggez::graphics;
+use ggez::graphics::{Text, Rect, Point};
+use ggez::event::{Keycode, MouseButton, MouseState};
+use ggez::Context;
+use mopa;
+
+use substate::states::StateInfo;
+use utility::atlas;
+use utility::audio;
+use utility::fonts::FontSelection;
+use utility::i18n;
+use utility::settings;
+use utility::theme::{Theme, Style, StyleDef};
+
+pub use self::dialog::{Dialog, CHOICE_YES, CHOICE_NO};
+pub use self::image::Image;
+pub use self::layout::{LayoutDef, ElementDef, ElementType};
+pub use self::list_view::ListView;
+pub use self::progress_bar::ProgressBar;
+pub use self::rich_label::{RichLabel, Alignment};
+pub use self::scroll::{ScrollPanel, ScrollState};
+pub use self::tween::{Tween, Easing, Lerp, ease};
+
+const TOOLTIP_DELAY_MS: u64 = 350;
+const TOOLTIP_CURSOR_OFFSET: f32 = 16.0;
+const HOVER_FADE_SECS: f32 = 0.15;
+
+pub struct UIContext {
+    elements: HashMap<&'static str, Box<UIElement>>,
+    // element names in the order they're drawn, oldest first, so later
+    // elements go on top
+    draw_order: Vec<&'static str>,
+    styles: HashMap<&'static str, Style>,
+    style_overrides: HashMap<&'static str, StyleDef>,
+    theme: Theme,
+    locale_generation: usize,
+    mouse_pos: (i32, i32),
+    tooltip: Option<Tooltip>,
+    tooltip_style: Style,
+    dialog: Option<Dialog>,
+    dialog_style: Style,
+    layouts: Vec<layout::LayoutWatch>,
+    needs_relayout: bool,
+    // text changed with the locale is only measured when it's next drawn, so
+    // the sub-state has to lay out again after that
+    relayout_after_draw: bool,
+}
+
+// the mouse and keyboard events a sub-state passes on to its UI, with the
+// same arguments as the event handlers they come from
+pub enum Input {
+    MouseMotion(MouseState, i32, i32, i32, i32),
+    MouseDown(MouseButton, i32, i32),
+    MouseUp(MouseButton, i32, i32),
+    Wheel(i32),
+    KeyDown(Keycode),
+}
+
+struct Tooltip {
+    contents: String,
+    hover_start: Instant,
+    font: FontSelection,
+    render_text: Option<Text>,
+}
+
+impl UIContext {
+    pub fn new() -> UIContext {
+        let theme = Theme::load();
+        let tooltip_style = theme.get_style("tooltip", "tooltip");
+        let dialog_style = theme.get_style("dialog", "dialog");
+
+        UIContext {
+            elements: HashMap::<&'static str, Box<UIElement>>::new(),
+            draw_order: Vec::<&'static str>::new(),
+            styles: HashMap::<&'static str, Style>::new(),
+            style_overrides: HashMap::<&'static str, StyleDef>::new(),
+            theme: theme,
+            locale_generation: i18n::generation(),
+            mouse_pos: (0, 0),
+            tooltip: None,
+            tooltip_style: tooltip_style,
+            dialog: None,
+            dialog_style: dialog_style,
+            layouts: Vec::<layout::LayoutWatch>::new(),
+            needs_relayout: false,
+            relayout_after_draw: false,
+        }
+    }
+
+    // returns true once after the locale has changed, having already refreshed
+    // the text of every localized element, and again after the new text has
+    // been drawn and measured; also once after a layout file has been
+    // reloaded. The owning sub-state should re-layout
+    pub fn is_dirty(&mut self) -> bool {
+        if self.needs_relayout {
+            self.needs_relayout = false;
+            return true;
+        }
+
+        let generation = i18n::generation();
+        if generation == self.locale_generation {
+            return false;
+        }
+
+        self.locale_generation = generation;
+        self.relayout_after_draw = true;
+        for e in self.elements.values_mut() {
+            e.locale_changed();
+        }
+        if let Some(ref mut dialog) = self.dialog {
+            dialog.locale_changed();
+        }
+        true
+    }
+
+    pub fn add_element<T>(&mut self, name: &'static str, element: Box<T>)
+    where
+        T: UIElement + 'static,
+    {
+        let style = self.resolve_style(element.widget_type(), name);
+        self.styles.insert(name, style);
+        // a replaced element keeps its place
+        if self.elements.insert(name, element).is_none() {
+            self.draw_order.push(name);
+        }
+    }
+
+    pub fn has_element(&self, name: &str) -> bool {
+        self.elements.contains_key(name)
+    }
+
+    pub fn remove_element(&mut self, name: &str) {
+        self.elements.remove(name);
+        self.draw_order.retain(|n| *n != name);
+        self.styles.remove(name);
+        self.style_overrides.remove(name);
+    }
+
+    // changes to one element's style on top of what the theme gives it
+    pub fn set_style_override(&mut self, name: &'static str, def: StyleDef) {
+        self.style_overrides.insert(name, def);
+        let widget_type = self.elements.get(name).map(|e| e.widget_type());
+        if let Some(widget_type) = widget_type {
+            let style = self.resolve_style(widget_type, name);
+            self.styles.insert(name, style);
+        }
+    }
+
+    fn resolve_style(&self, widget_type: &str, name: &'static str) -> Style {
+        let mut style = self.theme.get_style(widget_type, name);
+        if let Some(def) = self.style_overrides.get(name) {
+            style.apply(def);
+        }
+        style
+    }
+
+    // adds the elements described by a layout file, or by `fallback` (the JSON
+    // of a layout built into the game) if the file can't be loaded, returning
+    // why; in debug builds the file is watched either way, and saving it
+    // replaces the elements while the game runs
+    pub fn load_layout(&mut self, path: &str, fallback: &str) -> Result<(), String> {
+        match LayoutDef::from_file(path) {
+            Ok(layout) => {
+                let names = layout.build(self);
+                self.watch_layout(path, names);
+                Ok(())
+            }
+            Err(why) => {
+                let names = LayoutDef::from_json(fallback)?.build(self);
+                self.watch_layout(path, names);
+                Err(why)
+            }
+        }
+    }
+
+    fn watch_layout(&mut self, path: &str, names: Vec<&'static str>) {
+        if cfg!(debug_assertions) {
+            self.layouts.push(layout::LayoutWatch::new(path, names));
+        }
+    }
+
+    // a layout that fails to load leaves the elements it would have replaced
+    // alone, so a typo doesn't take the whole screen with it
+    fn reload_layouts(&mut self) {
+        let mut layouts = std::mem::replace(&mut self.layouts, Vec::<layout::LayoutWatch>::new());
+        for watch in layouts.iter_mut() {
+            if !watch.has_changed() {
+                continue;
+            }
+
+            match LayoutDef::from_file(watch.get_path()) {
+                Ok(def) => {
+                    for name in watch.get_names().iter() {
+                        self.remove_element(name);
+                    }
+                    watch.set_names(def.build(self));
+
+                    let (x, y) = self.mouse_pos;
+                    self.mouse_moved(x, y);
+                    self.needs_relayout = true;
+                    println!("Reloaded layout {}", watch.get_path());
+                }
+                Err(why) => println!("Unable to reload layout: {}", why),
+            }
+        }
+        self.layouts = layouts;
+    }
+
+    pub fn reload_theme(&mut self) {
+        self.theme = Theme::load();
+        let types = self.elements
+            .iter()
+            .map(|(name, e)| (*name, e.widget_type()))
+            .collect::<Vec<_>>();
+        for (name, widget_type) in types {
+            let style = self.resolve_style(widget_type, name);
+            self.styles.insert(name, style);
+        }
+        self.tooltip_style = self.theme.get_style("tooltip", "tooltip");
+        self.dialog_style = self.theme.get_style("dialog", "dialog");
+        if let Some(ref mut dialog) = self.dialog {
+            dialog.set_button_style(self.theme.get_style("button", "dialog_button"));
+        }
+    }
+
+    // opens a modal dialog on top of the other elements, replacing any dialog
+    // that's already open; it stays up until one of its buttons is clicked
+    pub fn show_dialog(&mut self, mut dialog: Dialog) {
+        dialog.set_button_style(self.theme.get_style("button", "dialog_button"));
+        let (x, y) = self.mouse_pos;
+        dialog.hover(x, y);
+        self.dialog = Some(dialog);
+        self.tooltip = None;
+    }
+
+    pub fn has_dialog(&self) -> bool {
+        self.dialog.is_some()
+    }
+
+    // hands an input event to the right element method; only the left button
+    // clicks
+    pub fn handle_input(&mut self, input: Input, info: &mut StateInfo) {
+        match input {
+            Input::MouseMotion(state, x, y, xrel, yrel) => {
+                if state.left() {
+                    self.mouse_dragged(x, y, xrel, yrel);
+                } else {
+                    self.mouse_moved(x, y);
+                }
+            }
+            Input::MouseDown(MouseButton::Left, x, y) => self.click(x, y, info),
+            Input::MouseUp(MouseButton::Left, x, y) => self.release(x, y, info),
+            Input::MouseDown(..) | Input::MouseUp(..) => {}
+            Input::Wheel(amount) => self.wheel(amount),
+            Input::KeyDown(keycode) => self.key_down(keycode, info),
+        }
+    }
+
+    pub fn mouse_moved(&mut self, x: i32, y: i32) {
+        self.mouse_pos = (x, y);
+        if let Some(ref mut dialog) = self.dialog {
+            dialog.hover(x, y);
+            return;
+        }
+
+        for e in self.elements.values_mut() {
+            e.hover(x, y);
+        }
+        self.update_tooltip();
+    }
+
+    // where elements overlap, the tooltip of the one drawn on top shows; the
+    // hover delay restarts whenever the text under the cursor changes, so
+    // sliding across a button doesn't keep re-showing the same tooltip
+    fn update_tooltip(&mut self) {
+        let (x, y) = self.mouse_pos;
+        let contents = if settings::read(|s| s.gameplay.show_tooltips) {
+            self.draw_order
+                .iter()
+                .rev()
+                .filter_map(|name| self.elements.get(name))
+                .filter_map(|e| e.tooltip(x, y))
+                .next()
+        } else {
+            None
+        };
+
+        match contents {
+            Some(contents) => {
+                let unchanged = match self.tooltip {
+                    Some(ref t) => t.contents == contents,
+                    None => false,
+                };
+
+                if !unchanged {
+                    self.tooltip = Some(Tooltip {
+                        contents: contents,
+                        hover_start: Instant::now(),
+                        font: FontSelection::new(),
+                        render_text: None,
+                    });
+                }
+            }
+            None => self.tooltip = None,
+        }
+    }
+
+    fn draw_tooltip(&mut self, ctx: &mut Context) {
+        let style = &self.tooltip_style;
+        let (mouse_x, mouse_y) = self.mouse_pos;
+
+        if let Some(ref mut tooltip) = self.tooltip {
+            if tooltip.hover_start.elapsed() < Duration::from_millis(TOOLTIP_DELAY_MS) {
+                return;
+            }
+
+            if tooltip.font.update(style) || tooltip.render_text.is_none() {
+                let mut txt = Text::new(ctx, &tooltip.contents, &tooltip.font.get()).unwrap();
+                txt.set_filter(graphics::FilterMode::Nearest);
+                tooltip.render_text = Some(txt);
+            }
+
+            if let Some(ref txt) = tooltip.render_text {
+                let width = txt.width() as f32 + 2.0 * style.horiz_padding;
+                let height = txt.height() as f32 + 2.0 * style.vert_padding;
+
+                // keep the whole box on-screen, flipping it to the other side
+                // of the cursor when it would run off the right or bottom edge
+                let screen = graphics::get_screen_coordinates(ctx);
+                let screen_w = screen.w;
+                let screen_h = screen.h * -1.0;
+                let mut left = mouse_x as f32 + TOOLTIP_CURSOR_OFFSET;
+                let mut top = mouse_y as f32 + TOOLTIP_CURSOR_OFFSET;
+                if left + width > screen_w {
+                    left = mouse_x as f32 - TOOLTIP_CURSOR_OFFSET - width;
+                }
+                if top + height > screen_h {
+                    top = mouse_y as f32 - TOOLTIP_CURSOR_OFFSET - height;
+                }
+                left = left.max(0.0).min((screen_w - width).max(0.0));
+                top = top.max(0.0).min((screen_h - height).max(0.0));
+
+                let rect = Rect::new(left + width / 2.0, top + height / 2.0, width, height);
+                graphics::set_color(ctx, style.color).unwrap();
+                graphics::rectangle(ctx, graphics::DrawMode::Fill, rect).unwrap();
+                graphics::set_line_width(ctx, style.border_width);
+                graphics::set_color(ctx, style.color_border).unwrap();
+                graphics::rectangle(ctx, graphics::DrawMode::Line, rect).unwrap();
+
+                graphics::set_color(ctx, style.font_color).unwrap();
+                graphics::draw(ctx, txt, Point::new(rect.x, rect.y), 0.0).unwrap();
+            }
+        }
+    }
+
+    pub fn click(&mut self, x: i32, y: i32, info: &mut StateInfo) {
+        if let Some(ref mut dialog) = self.dialog {
+            dialog.click(x, y, info);
+            return;
+        }
+
+        for e in self.elements.values_mut() {
+            e.click(x, y, info);
+        }
+    }
+
+    // mouse movement with the left button held down
+    pub fn mouse_dragged(&mut self, x: i32, y: i32, xrel: i32, yrel: i32) {
+        self.mouse_pos = (x, y);
+        if self.dialog.is_some() {
+            return;
+        }
+
+        for e in self.elements.values_mut() {
+            e.drag(x, y, xrel, yrel);
+        }
+    }
+
+    // buttons activate on release, so this is where dialogs get answered;
+    // anything held down under an open dialog lets go without firing
+    pub fn release(&mut self, x: i32, y: i32, info: &mut StateInfo) {
+        if let Some(mut dialog) = self.dialog.take() {
+            for e in self.elements.values_mut() {
+                e.cancel_press();
+            }
+
+            dialog.release(x, y, info);
+            if dialog.is_closed() {
+                //elements kept their hover state from before the dialog opened
+                for e in self.elements.values_mut() {
+                    e.hover(x, y);
+                }
+            } else {
+                self.dialog = Some(dialog);
+            }
+        } else {
+            for e in self.elements.values_mut() {
+                e.release(x, y, info);
+            }
+        }
+    }
+
+    // wheel amounts are positive when scrolling up; the wheel event itself
+    // doesn't carry a position, so the last known cursor position is used
+    pub fn wheel(&mut self, amount: i32) {
+        if self.dialog.is_some() {
+            return;
+        }
+
+        let (x, y) = self.mouse_pos;
+        for e in self.elements.values_mut() {
+            e.wheel(x, y, amount);
+        }
+    }
+
+    pub fn key_down(&mut self, keycode: Keycode, info: &mut StateInfo) {
+        if self.dialog.is_some() {
+            return;
+        }
+
+        for e in self.elements.values_mut() {
+            e.key_down(keycode, info);
+        }
+    }
+
+    // advances every element's animations; sub-states call this each update
+    pub fn update(&mut self, dt: Duration) {
+        if !self.layouts.is_empty() {
+            self.reload_layouts();
+        }
+
+        let dt = tween::seconds(dt);
+        for e in self.elements.values_mut() {
+            e.update(dt);
+        }
+        if let Some(ref mut dialog) = self.dialog {
+            dialog.update(dt);
+        }
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context) {
+        for name in self.draw_order.iter() {
+            if let (Some(e), Some(style)) = (self.elements.get_mut(name), self.styles.get(name)) {
+                e.draw(ctx, style);
+            }
+        }
+
+        if let Some(ref mut dialog) = self.dialog {
+            dialog.draw(ctx, &self.dialog_style);
+        } else {
+            self.draw_tooltip(ctx);
+        }
+
+        if self.relayout_after_draw {
+            self.relayout_after_draw = false;
+            self.needs_relayout = true;
+        }
+    }
+
+    pub fn modify_element<F, T>(&mut self, name: &'static str, mut op: F)
+    where
+        F: FnMut(&mut T),
+        T: UIElement,
+    {
+        if let Some(element) = self.elements.get_mut(name) {
+            if let Some(ref mut c) = element.downcast_mut::<T>() {
+                op(c);
+            } else {
+                panic!(
+                    "The requested element with name \"{}\" could not be downcast to type {:?}",
+                    name,
+                    std::any::TypeId::of::<T>()
+                )
+            }
+        } else {
+            panic!("No UI element found with name \"{}\"", name);
+        }
+    }
+}
+
+pub trait UIElement: mopa::Any {
+    fn draw(&mut self, ctx: &mut Context, style: &Style);
+    fn widget_type(&self) -> &'static str {
+        "default"
+    }
+    fn update(&mut self, dt: f32) {}
+    fn hover(&mut self, mouse_x: i32, mouse_y: i32) {}
+    fn click(&mut self, mouse_x: i32, mouse_y: i32, info: &mut StateInfo) {}
+    fn drag(&mut self, mouse_x: i32, mouse_y: i32, xrel: i32, yrel: i32) {}
+    fn release(&mut self, mouse_x: i32, mouse_y: i32, info: &mut StateInfo) {}
+    // lets go of whatever the mouse was holding without acting on it
+    fn cancel_press(&mut self) {}
+    fn wheel(&mut self, mouse_x: i32, mouse_y: i32, amount: i32) {}
+    fn key_down(&mut self, keycode: Keycode, info: &mut StateInfo) {}
+    fn locale_changed(&mut self) {}
+    fn tooltip(&self, mouse_x: i32, mouse_y: i32) -> Option<String> {
+        None
+    }
+}
+
+mopafy!(UIElement);
+
+//label
+pub struct Label {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    text_contents: String,
+    text_key: Option<&'static str>,
+    font: FontSelection,
+    render_text: Option<Text>,
+}
+
+impl Label {
+    pub fn new(text: String) -> Label {
+        Label {
+            x: 0.0,
+            y: 0.0,
+            width: 0.0,
+            height: 0.0,
+            text_contents: text,
+            text_key: None,
+            font: FontSelection::new(),
+            render_text: None,
+        }
+    }
+
+    // a label whose text is looked up from the string table, and looked up
+    // again whenever the locale changes
+    pub fn localized(key: &'static str) -> Label {
+        let mut label = Label::new(i18n::tr(key));
+        label.text_key = Some(key);
+        label
+    }
+
+    pub fn set_font(&mut self, name: &str, size: u32) {
+        self.font.set(name, size);
+        self.render_text = None;
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> f32 {
+        self.height
+    }
+
+    pub fn set_text(&mut self, text: String, ctx: &mut Context) {
+        self.text_contents = text;
+        self.rebuild_render_text(ctx);
+    }
+
+    fn rebuild_render_text(&mut self, ctx: &mut Context) {
+        let txt = Text::new(ctx, &self.text_contents, &self.font.get()).unwrap();
+        self.width = txt.width() as f32;
+        self.height = txt.height() as f32;
+        self.render_text = Some(txt);
+
+        if let Some(ref mut txt) = self.render_text {
+            txt.set_filter(graphics::FilterMode::Nearest);
+        }
+    }
+}
+
+impl UIElement for Label {
+    fn draw(&mut self, ctx: &mut Context, style: &Style) {
+        if self.font.update(style) || self.render_text.is_none() {
+            self.rebuild_render_text(ctx);
+        }
+
+        if let Some(ref txt) = self.render_text {
+            let text_pos = Point::new(self.x, self.y);
+            graphics::set_color(ctx, style.font_color).unwrap();
+            graphics::draw(ctx, txt, text_pos, 0.0).unwrap();
+        }
+    }
+
+    fn widget_type(&self) -> &'static str {
+        "label"
+    }
+
+    fn locale_changed(&mut self) {
+        if let Some(key) = self.text_key {
+            self.text_contents = i18n::tr(key);
+            self.render_text = None;
+        }
+    }
+}
+
+//button
+pub struct Button {
+    x: f32,
+    y: f32,
+    text_contents: String,
+    text_key: Option<&'static str>,
+    tooltip: Option<String>,
+    tooltip_key: Option<&'static str>,
+    font: FontSelection,
+    callback: fn(info: &mut StateInfo),
+    action: Option<String>,
+    hovered: bool,
+    pressed: bool,
+    enabled: bool,
+    checkable: bool,
+    checked: bool,
+    render_text: Option<Text>,
+    width: f32,
+    height: f32,
+    use_default_width: bool,
+    position: Tween<(f32, f32)>,
+    width_tween: Option<Tween<f32>>,
+    hover_fade: Tween<f32>,
+    alpha: Tween<f32>,
+}
+
+impl Button {
+    pub fn new(text: String, callback: fn(info: &mut StateInfo)) -> Button {
+        Button {
+            x: 0.0,
+            y: 0.0,
+            text_contents: text,
+            text_key: None,
+            tooltip: None,
+            tooltip_key: None,
+            font: FontSelection::new(),
+            callback: callback,
+            action: None,
+            hovered: false,
+            pressed: false,
+            enabled: true,
+            checkable: false,
+            checked: false,
+            render_text: None,
+            width: 0.0,
+            height: 0.0,
+            use_default_width: true,
+            position: Tween::fixed((0.0, 0.0)),
+            width_tween: None,
+            hover_fade: Tween::fixed(0.0),
+            alpha: Tween::fixed(1.0),
+        }
+    }
+
+    pub fn localized(key: &'static str, callback: fn(info: &mut StateInfo)) -> Button {
+        let mut button = Button::new(i18n::tr(key), callback);
+        button.text_key = Some(key);
+        button
+    }
+
+    // a button that flips between checked and unchecked each time it's
+    // activated, before calling the callback
+    pub fn toggle(text: String, callback: fn(info: &mut StateInfo)) -> Button {
+        let mut button = Button::new(text, callback);
+        button.checkable = true;
+        button
+    }
+
+    pub fn localized_toggle(key: &'static str, callback: fn(info: &mut StateInfo)) -> Button {
+        let mut button = Button::localized(key, callback);
+        button.checkable = true;
+        button
+    }
+
+    // disabled buttons ignore the mouse and are drawn greyed out
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.hovered = false;
+            self.pressed = false;
+            self.hover_fade.set(0.0);
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // held down, but not yet released
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    pub fn set_checked(&mut self, checked: bool) {
+        self.checked = checked;
+    }
+
+    pub fn is_checked(&self) -> bool {
+        self.checked
+    }
+
+    pub fn set_font(&mut self, name: &str, size: u32) {
+        self.font.set(name, size);
+        self.render_text = None;
+    }
+
+    pub fn set_width(&mut self, width: f32) {
+        self.width = width;
+        self.width_tween = None;
+        self.use_default_width = false;
+        self.render_text = None;
+    }
+
+    // grows or shrinks to the given width rather than snapping to it
+    pub fn resize_to(&mut self, width: f32, duration: f32, easing: Easing) {
+        let mut tween = self.width_tween.unwrap_or(Tween::fixed(self.width));
+        tween.retarget(width, duration, easing);
+        self.width_tween = Some(tween);
+        self.use_default_width = false;
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> f32 {
+        self.height
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+        self.position.set((x, y));
+    }
+
+    // slides to the given position rather than snapping to it
+    pub fn move_to(&mut self, x: f32, y: f32, duration: f32, easing: Easing) {
+        self.position.retarget((x, y), duration, easing);
+    }
+
+    // for when a plain move_to isn't enough, e.g. to add a delay
+    pub fn animate_position(&mut self, tween: Tween<(f32, f32)>) {
+        self.position = tween;
+        let (x, y) = tween.value();
+        self.x = x;
+        self.y = y;
+    }
+
+    // where the button is heading, rather than where it is right now
+    pub fn get_target_position(&self) -> (f32, f32) {
+        self.position.target()
+    }
+
+    pub fn set_alpha(&mut self, alpha: f32) {
+        self.alpha.set(alpha);
+    }
+
+    pub fn fade_to(&mut self, alpha: f32, duration: f32, easing: Easing) {
+        self.alpha.retarget(alpha, duration, easing);
+    }
+
+    pub fn set_text(&mut self, text: String) {
+        self.text_contents = text;
+        self.render_text = None;
+    }
+
+    pub fn set_tooltip(&mut self, tooltip: String) {
+        self.tooltip = Some(tooltip);
+        self.tooltip_key = None;
+    }
+
+    // a tooltip that follows the locale, like the button's text
+    pub fn set_localized_tooltip(&mut self, key: &'static str) {
+        self.tooltip = Some(i18n::tr(key));
+        self.tooltip_key = Some(key);
+    }
+
+    // an action passed to StateInfo::trigger on activation, after the callback
+    pub fn set_action(&mut self, action: String) {
+        self.action = Some(action);
+    }
+
+    pub fn is_hovered(&self) -> bool {
+        self.hovered
+    }
+
+    // builds the button's text (and so its size) ahead of the first draw, for
+    // containers that need to lay the button out before drawing it
+    pub fn prepare(&mut self, ctx: &mut Context, style: &Style) {
+        if self.font.update(style) || self.render_text.is_none() {
+            self.render_text = Some(
+                Text::new(ctx, &self.text_contents, &self.font.get()).unwrap(),
+            );
+            if let Some(ref mut txt) = self.render_text {
+                txt.set_filter(graphics::FilterMode::Nearest);
+                if self.use_default_width {
+                    self.width = txt.width() as f32 + (2.0 * style.horiz_padding);
+                }
+                self.height = txt.height() as f32 + (2.0 * style.vert_padding);
+            }
+        }
+    }
+
+    fn contains(&self, mouse_x: i32, mouse_y: i32) -> bool {
+        let mx = mouse_x as f32;
+        let my = mouse_y as f32;
+        mx >= self.x - (self.width / 2.0) && mx <= self.x + (self.width / 2.0) &&
+            my >= self.y - (self.height / 2.0) && my <= self.y + (self.height / 2.0)
+    }
+
+    // draws the theme's images for the button, with the hover image faded in
+    // over the normal one; false if the theme doesn't give it any
+    fn draw_artwork(&self, ctx: &mut Context, style: &Style, rect: &Rect, alpha: f32) -> bool {
+        let normal = match style.image {
+            Some(ref image) => image,
+            None => return false,
+        };
+
+        let pressed = self.enabled && ((self.pressed && self.hovered) || self.checked);
+        let base = if pressed {
+            style.image_pressed.as_ref().unwrap_or(normal)
+        } else {
+            normal
+        };
+        let tint = if self.enabled {
+            graphics::WHITE
+        } else {
+            style.color_disabled
+        };
+
+        if !atlas::draw(ctx, base, rect, with_alpha(tint, alpha)) {
+            return false;
+        }
+
+        let fade = self.hover_fade.value();
+        if let Some(ref hover) = style.image_hover {
+            if self.enabled && !pressed && fade > 0.0 {
+                atlas::draw(ctx, hover, rect, with_alpha(graphics::WHITE, alpha * fade));
+            }
+        }
+        true
+    }
+}
+
+// scales a color's alpha, for fading whole elements in and out
+fn with_alpha(color: graphics::Color, alpha: f32) -> graphics::Color {
+    graphics::Color::new(color.r, color.g, color.b, color.a * alpha)
+}
+
+impl UIElement for Button {
+    fn draw(&mut self, ctx: &mut Context, style: &Style) {
+        self.prepare(ctx, style);
+
+        let alpha = self.alpha.value();
+        if alpha <= 0.0 {
+            return;
+        }
+
+        let rect = Rect::new(self.x, self.y, self.width, self.height);
+
+        let (fill, font_color) = if !self.enabled {
+            (style.color_disabled, style.font_color_disabled)
+        } else if self.pressed && self.hovered {
+            (style.color_pressed, style.font_color)
+        } else {
+            let base = if self.checked {
+                style.color_checked
+            } else {
+                style.color
+            };
+            (
+                graphics::Color::lerp(base, style.color_hover, self.hover_fade.value()),
+                style.font_color,
+            )
+        };
+        if !self.draw_artwork(ctx, style, &rect, alpha) {
+            graphics::set_color(ctx, with_alpha(fill, alpha)).unwrap();
+            graphics::rectangle(ctx, graphics::DrawMode::Fill, rect).unwrap();
+
+            graphics::set_line_width(ctx, style.border_width);
+            graphics::set_color(ctx, with_alpha(style.color_border, alpha)).unwrap();
+            graphics::rectangle(ctx, graphics::DrawMode::Line, rect).unwrap();
+        }
+
+        let text_pos = Point::new(self.x, self.y);
+
+        if let Some(ref txt) = self.render_text {
+            graphics::set_color(ctx, with_alpha(font_color, alpha)).unwrap();
+            graphics::draw(ctx, txt, text_pos, 0.0).unwrap();
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.position.update(dt);
+        let (x, y) = self.position.value();
+        self.x = x;
+        self.y = y;
+
+        if let Some(ref mut tween) = self.width_tween {
+            tween.update(dt);
+            self.width = tween.value();
+        }
+
+        self.hover_fade.update(dt);
+        self.alpha.update(dt);
+    }
+
+    fn widget_type(&self) -> &'static str {
+        "button"
+    }
+
+    fn locale_changed(&mut self) {
+        if let Some(key) = self.text_key {
+            self.text_contents = i18n::tr(key);
+            self.render_text = None;
+        }
+        if let Some(key) = self.tooltip_key {
+            self.tooltip = Some(i18n::tr(key));
+        }
+    }
+
+    fn hover(&mut self, mouse_x: i32, mouse_y: i32) {
+        let hovered = self.enabled && self.contains(mouse_x, mouse_y);
+        if hovered && !self.hovered {
+            audio::play_effect(audio::SOUND_HOVER);
+        }
+        if hovered != self.hovered {
+            let target = if hovered { 1.0 } else { 0.0 };
+            self.hover_fade.retarget(target, HOVER_FADE_SECS, Easing::QuadOut);
+        }
+        self.hovered = hovered;
+    }
+
+    fn tooltip(&self, mouse_x: i32, mouse_y: i32) -> Option<String> {
+        if self.contains(mouse_x, mouse_y) {
+            self.tooltip.clone()
+        } else {
+            None
+        }
+    }
+
+    // pressing a button only arms it; it fires if the mouse is released
+    // while still over it
+    fn click(&mut self, mouse_x: i32, mouse_y: i32, info: &mut StateInfo) {
+        self.pressed = self.enabled && self.contains(mouse_x, mouse_y);
+    }
+
+    fn release(&mut self, mouse_x: i32, mouse_y: i32, info: &mut StateInfo) {
+        if !self.pressed {
+            return;
+        }
+
+        self.pressed = false;
+        if self.enabled && self.contains(mouse_x, mouse_y) {
+            if self.checkable {
+                self.checked = !self.checked;
+            }
+            audio::play_effect(audio::SOUND_CLICK);
+            (self.callback)(info);
+            if let Some(ref action) = self.action {
+                info.trigger(action);
+            }
+        }
+    }
+
+    fn cancel_press(&mut self) {
+        self.pressed = false;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ggez::event::MouseButton;
+
+    use substate::states::{StateInfo, StoredValue};
+    use super::{UIContext, UIElement, Button, Dialog, Input};
+
+    fn activated(info: &mut StateInfo) {
+        info.set_value("activated", StoredValue::Boolean { value: true });
+    }
+
+    fn was_activated(info: &mut StateInfo) -> bool {
+        info.take_value("activated").is_some()
+    }
+
+    fn button() -> Button {
+        let mut button = Button::toggle(String::from("test"), activated);
+        button.set_width(100.0);
+        button.set_position(50.0, 50.0);
+        button
+    }
+
+    #[test]
+    fn buttons_fire_on_release_over_the_button() {
+        let mut info = StateInfo::new();
+        let mut btn = button();
+
+        btn.click(50, 50, &mut info);
+        assert!(btn.is_pressed());
+        assert!(!was_activated(&mut info));
+
+        btn.release(50, 50, &mut info);
+        assert!(was_activated(&mut info));
+        assert!(btn.is_checked());
+
+        //dragging off before releasing cancels the press
+        btn.click(50, 50, &mut info);
+        btn.release(300, 50, &mut info);
+        assert!(!was_activated(&mut info));
+        assert!(btn.is_checked());
+    }
+
+    #[test]
+    fn only_the_left_button_clicks() {
+        let mut info = StateInfo::new();
+        let mut ui_context = UIContext::new();
+        ui_context.add_element("btn_test", Box::new(button()));
+
+        ui_context.handle_input(Input::MouseDown(MouseButton::Right, 50, 50), &mut info);
+        ui_context.handle_input(Input::MouseUp(MouseButton::Right, 50, 50), &mut info);
+        assert!(!was_activated(&mut info));
+
+        ui_context.handle_input(Input::MouseDown(MouseButton::Left, 50, 50), &mut info);
+        ui_context.handle_input(Input::MouseUp(MouseButton::Left, 50, 50), &mut info);
+        assert!(was_activated(&mut info));
+    }
+
+    #[test]
+    fn buttons_held_when_a_dialog_opens_never_fire() {
+        let mut info = StateInfo::new();
+        let mut ui_context = UIContext::new();
+        ui_context.add_element("btn_test", Box::new(button()));
+
+        ui_context.click(50, 50, &mut info);
+        ui_context.show_dialog(Dialog::confirm("test", "dialog.quit_title", "dialog.quit_message"));
+        ui_context.release(50, 50, &mut info);
+        assert!(!was_activated(&mut info));
+
+        //and the press is gone once the dialog has been dealt with
+        ui_context.modify_element("btn_test", |btn: &mut Button| {
+            assert!(!btn.is_pressed());
+        });
+    }
+
+    #[test]
+    fn the_top_element_shows_its_tooltip() {
+        let mut ui_context = UIContext::new();
+        for &(name, tooltip) in [("btn_a", "a"), ("btn_b", "b"), ("btn_c", "c")].iter() {
+            let mut btn = button();
+            btn.set_tooltip(String::from(tooltip));
+            ui_context.add_element(name, Box::new(btn));
+        }
+
+        ui_context.mouse_moved(50, 50);
+        assert_eq!(ui_context.tooltip.as_ref().unwrap().contents, "c");
+
+        //replacing an element leaves it where it was
+        ui_context.remove_element("btn_c");
+        let mut btn = button();
+        btn.set_tooltip(String::from("a again"));
+        ui_context.add_element("btn_a", Box::new(btn));
+        ui_context.mouse_moved(50, 50);
+        assert_eq!(ui_context.tooltip.as_ref().unwrap().contents, "b");
+    }
+
+    #[test]
+    fn disabled_buttons_ignore_the_mouse() {
+        let mut info = StateInfo::new();
+        let mut btn = button();
+        btn.set_enabled(false);
+
+        btn.hover(50, 50);
+        btn.click(50, 50, &mut info);
+        btn.release(50, 50, &mut info);
+        assert!(!btn.is_hovered());
+        assert!(!was_activated(&mut info));
+        assert!(!btn.is_checked());
+    }
+
+    #[test]
+    fn missing_layouts_fall_back_to_the_built_in_one() {
+        let mut ui_context = UIContext::new();
+        let fallback = r#"{ "elements": [ { "name": "btn_fallback", "type": "button", "text": "Go" } ] }"#;
+
+        let why = ui_context.load_layout("resources/layouts/missing.json", fallback);
+        assert!(why.unwrap_err().contains("missing.json"));
+        assert!(ui_context.has_element("btn_fallback"));
+    }
+}
@@ -0,0 +1,139 @@
+use ggez::graphics;
+use ggez::graphics::{Text, Rect, Point};
+use ggez::Context;
+
+use utility::fonts::FontSelection;
+use utility::theme::Style;
+use super::UIElement;
+
+// a bar that fills from the left as something gets done, with a caption
+// over the middle of it
+pub struct ProgressBar {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    progress: f32,
+    visible: bool,
+    text: String,
+    font: FontSelection,
+    render_text: Option<Text>,
+}
+
+impl ProgressBar {
+    pub fn new(width: f32, height: f32) -> ProgressBar {
+        ProgressBar {
+            x: 0.0,
+            y: 0.0,
+            width: width,
+            height: height,
+            progress: 0.0,
+            visible: true,
+            text: String::new(),
+            font: FontSelection::new(),
+            render_text: None,
+        }
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> f32 {
+        self.height
+    }
+
+    // from 0 to 1
+    pub fn set_progress(&mut self, progress: f32) {
+        self.progress = progress.max(0.0).min(1.0);
+    }
+
+    pub fn get_progress(&self) -> f32 {
+        self.progress
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn set_text(&mut self, text: String) {
+        if text != self.text {
+            self.text = text;
+            self.render_text = None;
+        }
+    }
+
+    fn fill_rect(&self) -> Rect {
+        let fill_width = self.width * self.progress;
+        Rect::new(
+            self.x - (self.width - fill_width) / 2.0,
+            self.y,
+            fill_width,
+            self.height,
+        )
+    }
+}
+
+impl UIElement for ProgressBar {
+    fn draw(&mut self, ctx: &mut Context, style: &Style) {
+        if !self.visible {
+            return;
+        }
+
+        if self.font.update(style) {
+            self.render_text = None;
+        }
+        if self.render_text.is_none() && !self.text.is_empty() {
+            let mut txt = Text::new(ctx, &self.text, &self.font.get()).unwrap();
+            txt.set_filter(graphics::FilterMode::Nearest);
+            self.render_text = Some(txt);
+        }
+
+        let rect = Rect::new(self.x, self.y, self.width, self.height);
+        graphics::set_color(ctx, style.color).unwrap();
+        graphics::rectangle(ctx, graphics::DrawMode::Fill, rect).unwrap();
+
+        if self.progress > 0.0 {
+            graphics::set_color(ctx, style.color_fill).unwrap();
+            graphics::rectangle(ctx, graphics::DrawMode::Fill, self.fill_rect()).unwrap();
+        }
+
+        if let Some(ref txt) = self.render_text {
+            graphics::set_color(ctx, style.font_color).unwrap();
+            graphics::draw(ctx, txt, Point::new(self.x, self.y), 0.0).unwrap();
+        }
+
+        graphics::set_line_width(ctx, style.border_width);
+        graphics::set_color(ctx, style.color_border).unwrap();
+        graphics::rectangle(ctx, graphics::DrawMode::Line, rect).unwrap();
+    }
+
+    fn widget_type(&self) -> &'static str {
+        "progress_bar"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ProgressBar;
+
+    #[test]
+    fn the_fill_grows_from_the_left_edge() {
+        let mut bar = ProgressBar::new(100.0, 10.0);
+        bar.set_position(200.0, 50.0);
+
+        bar.set_progress(0.25);
+        let fill = bar.fill_rect();
+        assert_eq!(fill.w, 25.0);
+        assert_eq!(fill.x - fill.w / 2.0, 150.0);
+
+        bar.set_progress(1.5);
+        assert_eq!(bar.get_progress(), 1.0);
+        assert_eq!(bar.fill_rect().x, 200.0);
+    }
+}
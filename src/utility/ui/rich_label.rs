@@ -0,0 +1,464 @@
+use ggez::graphics;
+use ggez::graphics::{Color, Text, Point, Rect};
+use ggez::Context;
+
+use utility::fonts::FontSelection;
+use utility::i18n;
+use utility::theme::Style;
+use super::UIElement;
+use super::scroll::draw_text_clipped;
+
+// faux-bold text is drawn twice, the second time shifted over by this much
+const BOLD_OFFSET: f32 = 1.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+// a run of text sharing the same formatting
+#[derive(Clone, PartialEq, Debug)]
+pub struct Span {
+    pub text: String,
+    pub bold: bool,
+    pub color: Option<String>,
+}
+
+// a piece of a laid-out line, positioned relative to the start of that line
+#[derive(Clone, PartialEq, Debug)]
+pub struct Fragment {
+    pub text: String,
+    pub span: usize,
+    pub x: f32,
+    pub width: f32,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Line {
+    pub fragments: Vec<Fragment>,
+    pub width: f32,
+}
+
+struct RenderedFragment {
+    text: Text,
+    x: f32,
+    y: f32,
+    bold: bool,
+    color: Color,
+}
+
+// splits markup into formatted spans; supports [b]..[/b] and
+// [color=r,g,b]..[/color] (or [color=name] for a theme palette entry), and
+// leaves anything else that looks like a tag as literal text
+pub fn parse_markup(markup: &str) -> Vec<Span> {
+    let mut spans = Vec::<Span>::new();
+    let mut bold_depth = 0;
+    let mut colors = Vec::<String>::new();
+    let mut current = String::new();
+    let mut rest = markup;
+
+    while !rest.is_empty() {
+        if rest.starts_with('[') {
+            if let Some(end) = rest.find(']') {
+                let tag = &rest[1..end];
+                let recognized = tag == "b" || (tag == "/b" && bold_depth > 0) ||
+                    tag.starts_with("color=") ||
+                    (tag == "/color" && !colors.is_empty());
+
+                if recognized {
+                    if !current.is_empty() {
+                        spans.push(Span {
+                            text: current.clone(),
+                            bold: bold_depth > 0,
+                            color: colors.last().cloned(),
+                        });
+                        current.clear();
+                    }
+
+                    if tag == "b" {
+                        bold_depth += 1;
+                    } else if tag == "/b" {
+                        bold_depth -= 1;
+                    } else if tag == "/color" {
+                        colors.pop();
+                    } else {
+                        colors.push(String::from(&tag["color=".len()..]));
+                    }
+
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+
+        let ch = rest.chars().next().unwrap();
+        current.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+
+    if !current.is_empty() {
+        spans.push(Span {
+            text: current,
+            bold: bold_depth > 0,
+            color: colors.last().cloned(),
+        });
+    }
+
+    spans
+}
+
+// breaks spans into lines no wider than max_width (a max_width of zero or less
+// disables wrapping); words that are wider than max_width on their own are
+// left to overflow on their own line
+pub fn layout_spans<F>(spans: &[Span], max_width: f32, space_width: f32, mut measure: F) -> Vec<Line>
+where
+    F: FnMut(&str, bool) -> f32,
+{
+    let mut lines = Vec::<Line>::new();
+    let mut line = Line {
+        fragments: Vec::<Fragment>::new(),
+        width: 0.0,
+    };
+    let mut pending_space = false;
+
+    for (index, span) in spans.iter().enumerate() {
+        for (n, paragraph) in span.text.split('\n').enumerate() {
+            if n > 0 {
+                lines.push(line);
+                line = Line {
+                    fragments: Vec::<Fragment>::new(),
+                    width: 0.0,
+                };
+                pending_space = false;
+            }
+
+            let mut word_start: Option<usize> = None;
+            let mut words = Vec::<(&str, bool)>::new();
+            let mut spaced = pending_space;
+            for (i, ch) in paragraph.char_indices() {
+                if ch.is_whitespace() {
+                    if let Some(start) = word_start {
+                        words.push((&paragraph[start..i], spaced));
+                        word_start = None;
+                    }
+                    spaced = true;
+                } else if word_start.is_none() {
+                    word_start = Some(i);
+                }
+            }
+            if let Some(start) = word_start {
+                words.push((&paragraph[start..], spaced));
+                spaced = false;
+            }
+            pending_space = spaced;
+
+            for (word, spaced) in words {
+                let word_width = measure(word, span.bold);
+                let gap = if spaced && !line.fragments.is_empty() {
+                    space_width
+                } else {
+                    0.0
+                };
+
+                // only break at whitespace; text glued to the previous span
+                // (e.g. "[b]bold[/b]er") has to stay on the same line
+                if max_width > 0.0 && spaced && !line.fragments.is_empty() &&
+                    line.width + gap + word_width > max_width
+                {
+                    lines.push(line);
+                    line = Line {
+                        fragments: Vec::<Fragment>::new(),
+                        width: 0.0,
+                    };
+                    append_word(&mut line, word, index, 0.0, word_width);
+                } else {
+                    append_word(&mut line, word, index, gap, word_width);
+                }
+            }
+        }
+    }
+
+    lines.push(line);
+    lines
+}
+
+fn append_word(line: &mut Line, word: &str, span: usize, gap: f32, width: f32) {
+    let x = line.width + gap;
+
+    if let Some(last) = line.fragments.last_mut() {
+        if last.span == span {
+            if gap > 0.0 {
+                last.text.push(' ');
+            }
+            last.text.push_str(word);
+            last.width = x + width - last.x;
+            line.width = x + width;
+            return;
+        }
+    }
+
+    line.fragments.push(Fragment {
+        text: String::from(word),
+        span: span,
+        x: x,
+        width: width,
+    });
+    line.width = x + width;
+}
+
+// parses "r,g,b" into a color, otherwise looks the name up in the style's palette
+fn resolve_color(color: &Option<String>, style: &Style) -> Color {
+    match *color {
+        Some(ref c) => {
+            let channels: Vec<u8> = c.split(',').filter_map(|v| v.trim().parse().ok()).collect();
+            if channels.len() == 3 {
+                Color::from((channels[0], channels[1], channels[2]))
+            } else {
+                match style.palette.get(c) {
+                    Some(color) => *color,
+                    None => style.font_color,
+                }
+            }
+        }
+        None => style.font_color,
+    }
+}
+
+//rich label
+pub struct RichLabel {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    max_width: f32,
+    alignment: Alignment,
+    spans: Vec<Span>,
+    text_key: Option<&'static str>,
+    font: FontSelection,
+    rendered: Option<Vec<RenderedFragment>>,
+}
+
+impl RichLabel {
+    pub fn new(markup: &str) -> RichLabel {
+        RichLabel {
+            x: 0.0,
+            y: 0.0,
+            width: 0.0,
+            height: 0.0,
+            max_width: 0.0,
+            alignment: Alignment::Left,
+            spans: parse_markup(markup),
+            text_key: None,
+            font: FontSelection::new(),
+            rendered: None,
+        }
+    }
+
+    pub fn localized(key: &'static str) -> RichLabel {
+        let mut label = RichLabel::new(&i18n::tr(key));
+        label.text_key = Some(key);
+        label
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    pub fn set_max_width(&mut self, max_width: f32) {
+        if self.max_width != max_width {
+            self.max_width = max_width;
+            self.rendered = None;
+        }
+    }
+
+    pub fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
+        self.rendered = None;
+    }
+
+    pub fn set_font(&mut self, name: &str, size: u32) {
+        self.font.set(name, size);
+        self.rendered = None;
+    }
+
+    pub fn set_text(&mut self, markup: &str) {
+        self.spans = parse_markup(markup);
+        self.rendered = None;
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> f32 {
+        self.height
+    }
+
+    // lays the text out ahead of the first draw so get_width/get_height are valid
+    pub fn prepare(&mut self, ctx: &mut Context, style: &Style) {
+        if self.font.update(style) || self.rendered.is_none() {
+            self.rebuild(ctx, style);
+        }
+    }
+
+    // draws only the parts of the label that fall inside clip
+    pub fn draw_clipped(&mut self, ctx: &mut Context, style: &Style, clip: &Rect) {
+        self.prepare(ctx, style);
+        self.draw_fragments(ctx, Some(clip));
+    }
+
+    fn draw_fragments(&self, ctx: &mut Context, clip: Option<&Rect>) {
+        let left = self.x - self.width / 2.0;
+        let top = self.y - self.height / 2.0;
+
+        if let Some(ref rendered) = self.rendered {
+            for fragment in rendered.iter() {
+                let pos = Point::new(left + fragment.x, top + fragment.y);
+                graphics::set_color(ctx, fragment.color).unwrap();
+
+                let mut positions = vec![pos];
+                if fragment.bold {
+                    positions.push(Point::new(pos.x + BOLD_OFFSET, pos.y));
+                }
+
+                for p in positions {
+                    match clip {
+                        Some(clip) => draw_text_clipped(ctx, &fragment.text, p, clip),
+                        None => graphics::draw(ctx, &fragment.text, p, 0.0).unwrap(),
+                    }
+                }
+            }
+        }
+    }
+
+    fn rebuild(&mut self, ctx: &mut Context, style: &Style) {
+        let font = self.font.get();
+        let line_height = font.get_height() as f32;
+        let space_width = font.get_width(" ") as f32;
+
+        let lines = layout_spans(&self.spans, self.max_width, space_width, |text, bold| {
+            let width = font.get_width(text) as f32;
+            if bold { width + BOLD_OFFSET } else { width }
+        });
+
+        self.width = if self.max_width > 0.0 {
+            self.max_width
+        } else {
+            lines.iter().fold(0.0, |w, l| if l.width > w { l.width } else { w })
+        };
+        self.height = line_height * lines.len() as f32;
+
+        let mut rendered = Vec::<RenderedFragment>::new();
+        for (i, line) in lines.iter().enumerate() {
+            let line_start = match self.alignment {
+                Alignment::Left => 0.0,
+                Alignment::Center => (self.width - line.width) / 2.0,
+                Alignment::Right => self.width - line.width,
+            };
+
+            for fragment in line.fragments.iter() {
+                let span = &self.spans[fragment.span];
+                let mut text = Text::new(ctx, &fragment.text, &font).unwrap();
+                text.set_filter(graphics::FilterMode::Nearest);
+
+                // fragment positions are relative to the top-left of the
+                // paragraph, while text is drawn centered on its position
+                rendered.push(RenderedFragment {
+                    x: line_start + fragment.x + text.width() as f32 / 2.0,
+                    y: (i as f32 + 0.5) * line_height,
+                    bold: span.bold,
+                    color: resolve_color(&span.color, style),
+                    text: text,
+                });
+            }
+        }
+
+        self.rendered = Some(rendered);
+    }
+}
+
+impl UIElement for RichLabel {
+    fn draw(&mut self, ctx: &mut Context, style: &Style) {
+        self.prepare(ctx, style);
+        self.draw_fragments(ctx, None);
+    }
+
+    fn widget_type(&self) -> &'static str {
+        "label"
+    }
+
+    fn locale_changed(&mut self) {
+        if let Some(key) = self.text_key {
+            self.spans = parse_markup(&i18n::tr(key));
+            self.rendered = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_markup, layout_spans, Span};
+
+    fn measure(text: &str, _bold: bool) -> f32 {
+        10.0 * text.chars().count() as f32
+    }
+
+    #[test]
+    fn markup_is_split_into_formatted_spans() {
+        let spans = parse_markup("plain [b]bold [color=255,0,0]red[/color][/b] [x]");
+
+        assert_eq!(
+            spans,
+            vec![
+                Span {
+                    text: String::from("plain "),
+                    bold: false,
+                    color: None,
+                },
+                Span {
+                    text: String::from("bold "),
+                    bold: true,
+                    color: None,
+                },
+                Span {
+                    text: String::from("red"),
+                    bold: true,
+                    color: Some(String::from("255,0,0")),
+                },
+                Span {
+                    text: String::from(" [x]"),
+                    bold: false,
+                    color: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn text_wraps_at_max_width() {
+        let spans = parse_markup("aaa bbb ccc dddd");
+        let lines = layout_spans(&spans, 85.0, 10.0, measure);
+
+        let text: Vec<&str> = lines
+            .iter()
+            .map(|l| l.fragments[0].text.as_str())
+            .collect();
+        assert_eq!(text, vec!["aaa bbb", "ccc dddd"]);
+        assert!(lines.iter().all(|l| l.width <= 85.0));
+    }
+
+    #[test]
+    fn explicit_newlines_and_spans_are_kept() {
+        let spans = parse_markup("one [b]two[/b]\nthree");
+        let lines = layout_spans(&spans, 0.0, 10.0, measure);
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].fragments.len(), 2);
+        assert_eq!(lines[0].fragments[1].text, "two");
+        assert_eq!(lines[0].fragments[1].x, 40.0);
+        assert_eq!(lines[1].fragments[0].text, "three");
+    }
+}
@@ -0,0 +1,354 @@
+use ggez::graphics;
+use ggez::graphics::{Text, Rect, Point, DrawParam};
+use ggez::event::Keycode;
+use ggez::Context;
+
+use substate::states::StateInfo;
+use utility::theme::Style;
+use super::{UIElement, RichLabel};
+
+pub const SCROLLBAR_WIDTH: f32 = 6.0;
+const WHEEL_STEP: f32 = 32.0;
+const KEY_STEP: f32 = 24.0;
+const MIN_THUMB_LENGTH: f32 = 12.0;
+
+// how far a view is scrolled through content that may be taller than the view
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ScrollState {
+    offset: f32,
+    content_size: f32,
+    viewport_size: f32,
+}
+
+impl ScrollState {
+    pub fn new() -> ScrollState {
+        ScrollState {
+            offset: 0.0,
+            content_size: 0.0,
+            viewport_size: 0.0,
+        }
+    }
+
+    pub fn get_offset(&self) -> f32 {
+        self.offset
+    }
+
+    pub fn max_offset(&self) -> f32 {
+        (self.content_size - self.viewport_size).max(0.0)
+    }
+
+    pub fn can_scroll(&self) -> bool {
+        self.max_offset() > 0.0
+    }
+
+    pub fn set_sizes(&mut self, content_size: f32, viewport_size: f32) {
+        self.content_size = content_size;
+        self.viewport_size = viewport_size;
+        self.clamp();
+    }
+
+    pub fn set_offset(&mut self, offset: f32) {
+        self.offset = offset;
+        self.clamp();
+    }
+
+    pub fn scroll_by(&mut self, delta: f32) {
+        let offset = self.offset + delta;
+        self.set_offset(offset);
+    }
+
+    // scrolls just far enough for the span [start, end) to be entirely visible
+    pub fn scroll_into_view(&mut self, start: f32, end: f32) {
+        if start < self.offset {
+            self.set_offset(start);
+        } else if end > self.offset + self.viewport_size {
+            let offset = end - self.viewport_size;
+            self.set_offset(offset);
+        }
+    }
+
+    // wheel amounts are positive when scrolling up, towards the start
+    pub fn wheel(&mut self, amount: i32) {
+        self.scroll_by(-amount as f32 * WHEEL_STEP);
+    }
+
+    // returns false for keys that don't scroll
+    pub fn key_down(&mut self, keycode: Keycode) -> bool {
+        let page = self.viewport_size;
+        match keycode {
+            Keycode::Up => self.scroll_by(-KEY_STEP),
+            Keycode::Down => self.scroll_by(KEY_STEP),
+            Keycode::PageUp => self.scroll_by(-page),
+            Keycode::PageDown => self.scroll_by(page),
+            Keycode::Home => self.set_offset(0.0),
+            Keycode::End => {
+                let end = self.max_offset();
+                self.set_offset(end);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    // the scrollbar thumb as (start, length) along a track of the given length
+    pub fn thumb(&self, track_length: f32) -> (f32, f32) {
+        if !self.can_scroll() {
+            return (0.0, track_length);
+        }
+
+        let length = (track_length * self.viewport_size / self.content_size)
+            .max(MIN_THUMB_LENGTH)
+            .min(track_length);
+        let start = (track_length - length) * self.offset / self.max_offset();
+        (start, length)
+    }
+
+    fn clamp(&mut self) {
+        self.offset = self.offset.max(0.0).min(self.max_offset());
+    }
+}
+
+// draws text centered on pos, cut off at the edges of clip; ggez has no
+// scissor support, so the visible part of the text's texture is picked out
+// with the draw call's source rect instead
+pub fn draw_text_clipped(ctx: &mut Context, text: &Text, pos: Point, clip: &Rect) {
+    let width = text.width() as f32;
+    let height = text.height() as f32;
+    let left = pos.x - width / 2.0;
+    let top = pos.y - height / 2.0;
+
+    let visible_left = left.max(clip.x - clip.w / 2.0);
+    let visible_right = (left + width).min(clip.x + clip.w / 2.0);
+    let visible_top = top.max(clip.y - clip.h / 2.0);
+    let visible_bottom = (top + height).min(clip.y + clip.h / 2.0);
+
+    if visible_right <= visible_left || visible_bottom <= visible_top {
+        return;
+    }
+
+    graphics::draw_ex(
+        ctx,
+        text,
+        DrawParam {
+            src: Rect::new(
+                (visible_left - left) / width,
+                (visible_top - top) / height,
+                (visible_right - visible_left) / width,
+                (visible_bottom - visible_top) / height,
+            ),
+            dest: Point::new(
+                (visible_left + visible_right) / 2.0,
+                (visible_top + visible_bottom) / 2.0,
+            ),
+            ..Default::default()
+        },
+    ).unwrap();
+}
+
+// draws a vertical scrollbar thumb along the right edge of the given rect
+pub fn draw_scrollbar(ctx: &mut Context, scroll: &ScrollState, rect: &Rect, style: &Style) {
+    if !scroll.can_scroll() {
+        return;
+    }
+
+    let (start, length) = scroll.thumb(rect.h);
+    let thumb = Rect::new(
+        rect.x + rect.w / 2.0 - SCROLLBAR_WIDTH / 2.0,
+        rect.y - rect.h / 2.0 + start + length / 2.0,
+        SCROLLBAR_WIDTH,
+        length,
+    );
+    graphics::set_color(ctx, style.color_hover).unwrap();
+    graphics::rectangle(ctx, graphics::DrawMode::Fill, thumb).unwrap();
+}
+
+//scroll panel
+pub struct ScrollPanel {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    content: RichLabel,
+    scroll: ScrollState,
+    dragging: bool,
+    focused: bool,
+}
+
+impl ScrollPanel {
+    pub fn new(content: RichLabel) -> ScrollPanel {
+        ScrollPanel {
+            x: 0.0,
+            y: 0.0,
+            width: 0.0,
+            height: 0.0,
+            content: content,
+            scroll: ScrollState::new(),
+            dragging: false,
+            focused: false,
+        }
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    pub fn set_size(&mut self, w: f32, h: f32) {
+        self.width = w;
+        self.height = h;
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> f32 {
+        self.height
+    }
+
+    pub fn get_content(&mut self) -> &mut RichLabel {
+        &mut self.content
+    }
+
+    pub fn scroll_to_top(&mut self) {
+        self.scroll.set_offset(0.0);
+    }
+
+    fn contains(&self, mouse_x: i32, mouse_y: i32) -> bool {
+        let mx = mouse_x as f32;
+        let my = mouse_y as f32;
+        mx >= self.x - (self.width / 2.0) && mx <= self.x + (self.width / 2.0) &&
+            my >= self.y - (self.height / 2.0) && my <= self.y + (self.height / 2.0)
+    }
+}
+
+impl UIElement for ScrollPanel {
+    fn draw(&mut self, ctx: &mut Context, style: &Style) {
+        let rect = Rect::new(self.x, self.y, self.width, self.height);
+        let inner = Rect::new(
+            self.x - SCROLLBAR_WIDTH / 2.0,
+            self.y,
+            self.width - 2.0 * style.horiz_padding - SCROLLBAR_WIDTH,
+            self.height - 2.0 * style.vert_padding,
+        );
+
+        self.content.set_max_width(inner.w);
+        self.content.prepare(ctx, style);
+        self.scroll.set_sizes(self.content.get_height(), inner.h);
+
+        let content_height = self.content.get_height();
+        self.content.set_position(
+            inner.x,
+            inner.y - inner.h / 2.0 - self.scroll.get_offset() + content_height / 2.0,
+        );
+
+        graphics::set_color(ctx, style.color).unwrap();
+        graphics::rectangle(ctx, graphics::DrawMode::Fill, rect).unwrap();
+
+        self.content.draw_clipped(ctx, style, &inner);
+        draw_scrollbar(ctx, &self.scroll, &rect, style);
+
+        graphics::set_line_width(ctx, style.border_width);
+        graphics::set_color(ctx, style.color_border).unwrap();
+        graphics::rectangle(ctx, graphics::DrawMode::Line, rect).unwrap();
+    }
+
+    fn widget_type(&self) -> &'static str {
+        "scroll_panel"
+    }
+
+    fn click(&mut self, mouse_x: i32, mouse_y: i32, _info: &mut StateInfo) {
+        self.focused = self.contains(mouse_x, mouse_y);
+        self.dragging = self.focused;
+    }
+
+    fn drag(&mut self, _mouse_x: i32, _mouse_y: i32, _xrel: i32, yrel: i32) {
+        if self.dragging {
+            self.scroll.scroll_by(-yrel as f32);
+        }
+    }
+
+    fn release(&mut self, _mouse_x: i32, _mouse_y: i32, _info: &mut StateInfo) {
+        self.dragging = false;
+    }
+
+    fn cancel_press(&mut self) {
+        self.dragging = false;
+    }
+
+    fn wheel(&mut self, mouse_x: i32, mouse_y: i32, amount: i32) {
+        if self.contains(mouse_x, mouse_y) {
+            self.scroll.wheel(amount);
+        }
+    }
+
+    fn key_down(&mut self, keycode: Keycode, _info: &mut StateInfo) {
+        if self.focused {
+            self.scroll.key_down(keycode);
+        }
+    }
+
+    fn locale_changed(&mut self) {
+        self.content.locale_changed();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ggez::event::Keycode;
+    use super::ScrollState;
+
+    #[test]
+    fn offset_is_clamped_to_the_content() {
+        let mut scroll = ScrollState::new();
+        scroll.set_sizes(500.0, 200.0);
+
+        scroll.scroll_by(-50.0);
+        assert_eq!(scroll.get_offset(), 0.0);
+
+        scroll.scroll_by(1000.0);
+        assert_eq!(scroll.get_offset(), 300.0);
+
+        //shrinking the content pulls the offset back in
+        scroll.set_sizes(250.0, 200.0);
+        assert_eq!(scroll.get_offset(), 50.0);
+
+        //content that fits can't be scrolled at all
+        scroll.set_sizes(100.0, 200.0);
+        assert_eq!(scroll.get_offset(), 0.0);
+        assert!(!scroll.can_scroll());
+    }
+
+    #[test]
+    fn wheel_and_keys_scroll_in_the_expected_direction() {
+        let mut scroll = ScrollState::new();
+        scroll.set_sizes(1000.0, 200.0);
+
+        scroll.wheel(-1);
+        assert!(scroll.get_offset() > 0.0);
+        scroll.wheel(1);
+        assert_eq!(scroll.get_offset(), 0.0);
+
+        assert!(scroll.key_down(Keycode::End));
+        assert_eq!(scroll.get_offset(), 800.0);
+        assert!(scroll.key_down(Keycode::PageUp));
+        assert_eq!(scroll.get_offset(), 600.0);
+        assert!(!scroll.key_down(Keycode::A));
+    }
+
+    #[test]
+    fn scroll_into_view_moves_the_least_distance() {
+        let mut scroll = ScrollState::new();
+        scroll.set_sizes(1000.0, 200.0);
+
+        scroll.scroll_into_view(250.0, 280.0);
+        assert_eq!(scroll.get_offset(), 80.0);
+
+        //already visible, so nothing moves
+        scroll.scroll_into_view(100.0, 150.0);
+        assert_eq!(scroll.get_offset(), 80.0);
+
+        scroll.scroll_into_view(40.0, 70.0);
+        assert_eq!(scroll.get_offset(), 40.0);
+    }
+}
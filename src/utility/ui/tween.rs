@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+use ggez::graphics::Color;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicOut,
+    // overshoots the target a little before settling on it
+    BackOut,
+}
+
+// maps progress through a tween, from 0 to 1, onto how far the value has
+// moved between its start and end
+pub fn ease(easing: Easing, t: f32) -> f32 {
+    let t = t.max(0.0).min(1.0);
+    match easing {
+        Easing::Linear => t,
+        Easing::QuadIn => t * t,
+        Easing::QuadOut => t * (2.0 - t),
+        Easing::QuadInOut => {
+            if t < 0.5 {
+                2.0 * t * t
+            } else {
+                -1.0 + (4.0 - 2.0 * t) * t
+            }
+        }
+        Easing::CubicOut => {
+            let u = t - 1.0;
+            u * u * u + 1.0
+        }
+        Easing::BackOut => {
+            let s = 1.70158;
+            let u = t - 1.0;
+            u * u * ((s + 1.0) * u + s) + 1.0
+        }
+    }
+}
+
+pub fn seconds(dt: Duration) -> f32 {
+    dt.as_secs() as f32 + dt.subsec_nanos() as f32 / 1_000_000_000.0
+}
+
+// anything that can be blended between two values
+pub trait Lerp: Copy + PartialEq {
+    fn lerp(from: Self, to: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(from: f32, to: f32, t: f32) -> f32 {
+        from + (to - from) * t
+    }
+}
+
+// positions and sizes
+impl Lerp for (f32, f32) {
+    fn lerp(from: (f32, f32), to: (f32, f32), t: f32) -> (f32, f32) {
+        (f32::lerp(from.0, to.0, t), f32::lerp(from.1, to.1, t))
+    }
+}
+
+impl Lerp for Color {
+    fn lerp(from: Color, to: Color, t: f32) -> Color {
+        let channel = |a: f32, b: f32| f32::lerp(a, b, t).max(0.0).min(1.0);
+        Color::new(
+            channel(from.r, to.r),
+            channel(from.g, to.g),
+            channel(from.b, to.b),
+            channel(from.a, to.a),
+        )
+    }
+}
+
+// moves a value from one point to another over time; elements own their
+// tweens and advance them from UIElement::update
+#[derive(Clone, Copy, Debug)]
+pub struct Tween<T: Lerp> {
+    from: T,
+    to: T,
+    duration: f32,
+    delay: f32,
+    elapsed: f32,
+    easing: Easing,
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(from: T, to: T, duration: f32, easing: Easing) -> Tween<T> {
+        Tween {
+            from: from,
+            to: to,
+            duration: duration,
+            delay: 0.0,
+            elapsed: 0.0,
+            easing: easing,
+        }
+    }
+
+    // a tween that has already finished at the given value
+    pub fn fixed(value: T) -> Tween<T> {
+        Tween::new(value, value, 0.0, Easing::Linear)
+    }
+
+    // holds the starting value for a while before moving
+    pub fn with_delay(mut self, delay: f32) -> Tween<T> {
+        self.delay = delay;
+        self
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.elapsed += dt;
+    }
+
+    pub fn value(&self) -> T {
+        if self.is_finished() {
+            return self.to;
+        }
+
+        let t = ((self.elapsed - self.delay) / self.duration).max(0.0);
+        T::lerp(self.from, self.to, ease(self.easing, t))
+    }
+
+    pub fn target(&self) -> T {
+        self.to
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.delay + self.duration
+    }
+
+    // heads for a new target from wherever the value is now; a tween that's
+    // already heading there is left alone
+    pub fn retarget(&mut self, to: T, duration: f32, easing: Easing) {
+        if to == self.to {
+            return;
+        }
+
+        *self = Tween::new(self.value(), to, duration, easing);
+    }
+
+    // jumps straight to the value
+    pub fn set(&mut self, value: T) {
+        *self = Tween::fixed(value);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ease, Easing, Tween};
+
+    #[test]
+    fn easings_start_and_end_in_place() {
+        let easings = [
+            Easing::Linear,
+            Easing::QuadIn,
+            Easing::QuadOut,
+            Easing::QuadInOut,
+            Easing::CubicOut,
+            Easing::BackOut,
+        ];
+        for easing in easings.iter() {
+            assert!(ease(*easing, 0.0).abs() < 0.0001, "{:?}", easing);
+            assert!((ease(*easing, 1.0) - 1.0).abs() < 0.0001, "{:?}", easing);
+        }
+
+        assert!(ease(Easing::QuadIn, 0.5) < 0.5);
+        assert!(ease(Easing::QuadOut, 0.5) > 0.5);
+        assert!(ease(Easing::BackOut, 0.8) > 1.0);
+    }
+
+    #[test]
+    fn tweens_wait_out_their_delay_then_move() {
+        let mut tween = Tween::new(0.0, 10.0, 1.0, Easing::Linear).with_delay(0.5);
+        tween.update(0.5);
+        assert_eq!(tween.value(), 0.0);
+
+        tween.update(0.5);
+        assert!((tween.value() - 5.0).abs() < 0.0001);
+
+        tween.update(1.0);
+        assert!(tween.is_finished());
+        assert_eq!(tween.value(), 10.0);
+    }
+
+    #[test]
+    fn retargeting_starts_from_the_current_value() {
+        let mut tween = Tween::new((0.0, 0.0), (10.0, 20.0), 1.0, Easing::Linear);
+        tween.update(0.5);
+        tween.retarget((0.0, 0.0), 1.0, Easing::Linear);
+        assert_eq!(tween.value(), (5.0, 10.0));
+
+        //heading for the same target again doesn't restart it
+        tween.update(0.5);
+        tween.retarget((0.0, 0.0), 1.0, Easing::Linear);
+        assert_eq!(tween.value(), (2.5, 5.0));
+    }
+}
@@ -0,0 +1,100 @@
+use ggez::Context;
+use ggez::graphics;
+
+// layouts are written against this resolution; larger or smaller windows scale
+// it uniformly and get the leftover space along one axis
+pub const VIRTUAL_WIDTH: f32 = 1024.0;
+pub const VIRTUAL_HEIGHT: f32 = 768.0;
+
+// keeps the UI from becoming unreadably small in tiny windows
+const MIN_UI_SCALE: f32 = 0.5;
+
+pub fn ui_scale_for(window_width: u32, window_height: u32) -> f32 {
+    let scale = (window_width as f32 / VIRTUAL_WIDTH).min(window_height as f32 / VIRTUAL_HEIGHT);
+    scale.max(MIN_UI_SCALE)
+}
+
+// the size of the area the UI is laid out in, in virtual units
+pub fn virtual_size(window_width: u32, window_height: u32) -> (f32, f32) {
+    let scale = ui_scale_for(window_width, window_height);
+    (window_width as f32 / scale, window_height as f32 / scale)
+}
+
+// points the screen coordinates at the virtual area for the given window size
+// and returns the scale factor from virtual units to window pixels
+pub fn apply(ctx: &mut Context, window_width: u32, window_height: u32) -> f32 {
+    let (width, height) = virtual_size(window_width, window_height);
+    graphics::set_screen_coordinates(ctx, 0.0, width, 0.0, height).unwrap();
+    ui_scale_for(window_width, window_height)
+}
+
+// converts a position in window pixels to virtual units
+pub fn to_virtual(x: i32, y: i32, scale: f32) -> (i32, i32) {
+    (
+        (x as f32 / scale).round() as i32,
+        (y as f32 / scale).round() as i32,
+    )
+}
+
+// converts a mouse movement in window pixels to virtual units; rounding each
+// movement on its own would lose slow ones entirely at large scales, so the
+// part that doesn't make a whole unit is kept in `remainder` and carried
+// over to the next
+pub fn to_virtual_motion(
+    xrel: i32,
+    yrel: i32,
+    scale: f32,
+    remainder: &mut (f32, f32),
+) -> (i32, i32) {
+    let x = xrel as f32 / scale + remainder.0;
+    let y = yrel as f32 / scale + remainder.1;
+    *remainder = (x.fract(), y.fract());
+    (x.trunc() as i32, y.trunc() as i32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ui_scale_for, virtual_size, to_virtual, to_virtual_motion, VIRTUAL_WIDTH,
+                VIRTUAL_HEIGHT};
+
+    #[test]
+    fn scale_fits_the_virtual_resolution_into_the_window() {
+        assert_eq!(ui_scale_for(1024, 768), 1.0);
+        assert_eq!(ui_scale_for(2048, 1536), 2.0);
+
+        //the narrower axis decides the scale, the other one gets extra room
+        assert_eq!(ui_scale_for(2048, 768), 1.0);
+        let (w, h) = virtual_size(2048, 768);
+        assert_eq!((w, h), (2048.0, VIRTUAL_HEIGHT));
+
+        let (w, h) = virtual_size(1280, 1440);
+        assert_eq!(w, VIRTUAL_WIDTH);
+        assert!(h > VIRTUAL_HEIGHT);
+
+        //never shrinks below the minimum
+        assert_eq!(ui_scale_for(100, 100), 0.5);
+    }
+
+    #[test]
+    fn mouse_positions_are_converted_to_virtual_units() {
+        assert_eq!(to_virtual(200, 100, 2.0), (100, 50));
+        assert_eq!(to_virtual(300, 150, 1.5), (200, 100));
+    }
+
+    #[test]
+    fn slow_mouse_movements_add_up() {
+        //one pixel at a time is less than a unit, but doesn't get lost
+        let mut remainder = (0.0, 0.0);
+        let mut total = (0, 0);
+        for _ in 0..30 {
+            let (x, y) = to_virtual_motion(1, -1, 2.8, &mut remainder);
+            total = (total.0 + x, total.1 + y);
+        }
+        assert_eq!(total, (10, -10));
+
+        //and a fast one isn't rounded up or down
+        let mut remainder = (0.0, 0.0);
+        assert_eq!(to_virtual_motion(7, 0, 2.0, &mut remainder), (3, 0));
+        assert_eq!(to_virtual_motion(1, 0, 2.0, &mut remainder), (1, 0));
+    }
+}